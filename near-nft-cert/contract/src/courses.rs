@@ -0,0 +1,139 @@
+use crate::*;
+
+/// A course or program an issuer awards certificates for. Certificates refer
+/// to it through `CertContent::course_id`.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Course {
+    pub id: String,
+    pub name: String,
+    pub level: Option<String>,
+    pub description: Option<String>,
+    pub credit_value: Option<u32>,
+    /// How long certificates stay valid from their start, in nanoseconds.
+    /// `None` means they don't expire.
+    pub validity_period: Option<U64>,
+    /// Artwork used for certificates created without their own `media_uri`.
+    pub artwork_uri: Option<String>,
+    pub artwork_hash: Option<String>,
+}
+
+impl Course {
+    pub fn assert_valid(&self) {
+        assert!(!self.id.is_empty(), "Course id is required");
+        assert!(!self.name.is_empty(), "Course name is required");
+        if let Some(validity_period) = self.validity_period {
+            assert!(validity_period.0 > 0, "validity_period must be positive");
+        }
+        assert_eq!(
+            self.artwork_uri.is_some(),
+            self.artwork_hash.is_some(),
+            "artwork_uri and artwork_hash must be set together"
+            );
+        if let Some(artwork_hash) = &self.artwork_hash {
+            decode_media_hash(artwork_hash);
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Registers a course of the calling issuer. Registering an existing id
+    /// again replaces the course.
+    pub fn register_course(&mut self, course: Course) -> Course {
+        let issuer = self.assert_role(Role::Issuer);
+        self.assert_active_issuer(&issuer);
+        course.assert_valid();
+        let initial_storage_usage = env::storage_usage();
+
+        let mut courses = self.courses.get(issuer.as_ref()).unwrap_or_else(|| {
+            UnorderedMap::new(StorageKey::CoursesInner {
+                account_hash: env::sha256(issuer.to_string().as_bytes()),
+            })
+        });
+        courses.insert(&course.id, &course);
+        self.courses.insert(issuer.as_ref(), &courses);
+
+        self.internal_charge_storage(&issuer.to_string(), initial_storage_usage);
+        return course;
+    }
+
+    pub fn get_course(&self, issuer: ValidAccountId, course_id: String) -> Option<Course> {
+        return self
+            .courses
+            .get(issuer.as_ref())
+            .and_then(|courses| courses.get(&course_id));
+    }
+
+    pub fn courses_of(&self, issuer: ValidAccountId, from_index: Option<U64>, limit: Option<u64>) -> Vec<Course> {
+        let courses = match self.courses.get(issuer.as_ref()) {
+            Some(courses) => courses,
+            None => return vec![],
+        };
+        let courses = courses.values_as_vector();
+        let (start, end) = page_bounds(courses.len(), from_index, limit);
+        return (start..end).map(|index| courses.get(index).unwrap()).collect();
+    }
+
+    pub fn certs_by_course(
+        &self,
+        issuer: ValidAccountId,
+        course_id: String,
+        from_index: Option<U64>,
+        limit: Option<u64>,
+        ) -> Vec<Certificate> {
+        return match self.certs_per_course.get(&course_key(&issuer.to_string(), &course_id)) {
+            Some(cert_ids) => self.internal_certs_page(cert_ids.as_vector(), from_index, limit),
+            None => vec![],
+        };
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_get_course(&self, issuer: &ValidAccountId, course_id: &str) -> Course {
+        return self
+            .courses
+            .get(issuer.as_ref())
+            .and_then(|courses| courses.get(&course_id.to_string()))
+            .unwrap_or_else(|| env::panic(format!("Course {} is not registered by {}", course_id, issuer).as_bytes()));
+    }
+
+    /// Fills what a certificate leaves out from its course: artwork, credit
+    /// hours and, from the validity period, the expiry.
+    pub(crate) fn internal_apply_course(&self, issuer: &ValidAccountId, mut input: CertInput) -> CertInput {
+        let course = self.internal_get_course(issuer, &input.content.course_id);
+
+        if input.media_uri.is_empty() {
+            if let (Some(artwork_uri), Some(artwork_hash)) = (course.artwork_uri, course.artwork_hash) {
+                input.media_uri = artwork_uri;
+                input.media_hash = artwork_hash;
+            }
+        }
+        if input.content.credit_hours.is_none() {
+            input.content.credit_hours = course.credit_value;
+        }
+        if input.expires_at.is_none() {
+            if let Some(validity_period) = course.validity_period {
+                let starts_at = input.starts_at.map_or(env::block_timestamp(), |t| t.0);
+                input.expires_at = Some(U64(starts_at + validity_period.0));
+            }
+        }
+        return input;
+    }
+
+    pub(crate) fn internal_add_cert_to_course(&mut self, cert: &Certificate) {
+        let key = course_key(&cert.issuer_account.to_string(), &cert.content.course_id);
+        let mut cert_ids = self.certs_per_course.get(&key).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::CertsPerCourseInner {
+                course_hash: env::sha256(key.as_bytes()),
+            })
+        });
+        cert_ids.insert(&cert.id);
+        self.certs_per_course.insert(&key, &cert_ids);
+    }
+}
+
+// Account ids can't contain '/', so the key is unambiguous.
+fn course_key(issuer: &str, course_id: &str) -> String {
+    return format!("{}/{}", issuer, course_id);
+}
@@ -1,19 +1,44 @@
+// Explicit `return`s are the house style, and `new_cert` mirrors the
+// certificate fields one argument each.
+#![allow(clippy::needless_return, clippy::too_many_arguments)]
+
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::UnorderedMap;
+use near_sdk::collections::{LookupMap, TreeMap, UnorderedMap, UnorderedSet};
 use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
 
 use near_contract_standards::non_fungible_token::metadata::{
     NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata, NFT_METADATA_SPEC,
 };
 use near_contract_standards::non_fungible_token::{Token, TokenId};
 use near_contract_standards::non_fungible_token::NonFungibleToken;
+use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApproval;
+use near_contract_standards::non_fungible_token::core::{
+    NonFungibleTokenCore, NonFungibleTokenResolver,
+};
+use std::collections::HashMap;
 use near_sdk::collections::LazyOption;
 use std::convert::TryFrom;
-use near_sdk::json_types::ValidAccountId;
+use near_sdk::json_types::{Base64VecU8, ValidAccountId, U64};
 use near_sdk::{
-    setup_alloc, env, near_bindgen, AccountId, BorshStorageKey, Promise, PromiseOrValue,
+    setup_alloc, env, near_bindgen, AccountId, Balance, BorshStorageKey, Gas, Promise,
+    PromiseOrValue,
 };
 
+mod courses;
+mod multisig;
+mod quota;
+mod roles;
+mod storage_impl;
+mod views;
+
+use crate::courses::Course;
+use crate::multisig::{Council, Proposal};
+use crate::views::page_bounds;
+use crate::quota::{IssuanceUsage, IssuerQuota};
+use crate::roles::{Role, StaffDelegation, StaffScope};
+use crate::storage_impl::StorageAccount;
+
 setup_alloc!();
 
 #[derive(BorshSerialize, BorshStorageKey)]
@@ -23,33 +48,332 @@ enum StorageKey {
     TokenMetadata,
     Enumeration,
     Approval,
+    CertsPerOwner,
+    CertsPerOwnerInner { account_hash: Vec<u8> },
+    Revocations,
+    TokensPerOwnerInner { account_hash: Vec<u8> },
+    StorageAccounts,
+    Roles,
+    IssuerStaff,
+    StaffPerIssuer,
+    StaffPerIssuerInner { account_hash: Vec<u8> },
+    Proposals,
+    IssuerQuotas,
+    IssuanceUsage,
+    CertsPerIssuer,
+    CertsPerIssuerInner { account_hash: Vec<u8> },
+    CertsPerStatus,
+    CertsPerStatusInner { status: CertStatus },
+    MintedByExpiry,
+    IssuerCertCounts,
+    Courses,
+    CoursesInner { account_hash: Vec<u8> },
+    CertsPerCourse,
+    CertsPerCourseInner { course_hash: Vec<u8> },
 }
 
 // DEFINE MODEL:
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum CertStatus {
+    Draft,
+    PendingReview,
+    Approved,
+    Rejected,
+    Minted,
+    Revoked,
+    Superseded,
+}
+
+impl CertStatus {
+    pub fn can_transition_to(&self, to: CertStatus) -> bool {
+        matches!(
+            (self, to),
+            (CertStatus::Draft, CertStatus::PendingReview)
+                | (CertStatus::Rejected, CertStatus::PendingReview)
+                | (CertStatus::PendingReview, CertStatus::Approved)
+                | (CertStatus::PendingReview, CertStatus::Rejected)
+                | (CertStatus::Approved, CertStatus::Minted)
+                | (CertStatus::Approved, CertStatus::Revoked)
+                | (CertStatus::Minted, CertStatus::Revoked)
+                | (CertStatus::Minted, CertStatus::Superseded)
+                | (CertStatus::Superseded, CertStatus::Revoked)
+            )
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StatusTransition {
+    pub from: CertStatus,
+    pub to: CertStatus,
+    pub actor: AccountId,
+    pub timestamp: U64,
+    pub reason: Option<String>,
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Clone, Serialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Certificate {
+    pub id: TokenId,
     pub owner_name: String,
     pub issuer_account: ValidAccountId,
-    pub is_approved: bool,
+    /// Issuer name and status as they were when the certificate was created.
+    pub issuer_name: String,
+    pub issuer_status: IssuerStatus,
+    /// Account that created the certificate, the issuer or one of its staff.
+    pub authored_by: AccountId,
+    pub status: CertStatus,
+    pub history: Vec<StatusTransition>,
+    pub content: CertContent,
     pub metadata: TokenMetadata,
-    pub owner_account: ValidAccountId 
+    pub owner_account: ValidAccountId,
+    pub renewal_of: Option<TokenId>,
+    pub superseded_by: Option<TokenId>,
+}
+
+impl Certificate {
+    pub fn starts_at(&self) -> Option<u64> {
+        return parse_timestamp(&self.metadata.starts_at);
+    }
+
+    pub fn expires_at(&self) -> Option<u64> {
+        return parse_timestamp(&self.metadata.expires_at);
+    }
+
+    pub fn validity_at(&self, timestamp: u64) -> CertValidity {
+        if self.starts_at().is_some_and(|starts_at| timestamp < starts_at) {
+            return CertValidity::NotYetValid;
+        }
+        if self.expires_at().is_some_and(|expires_at| timestamp >= expires_at) {
+            return CertValidity::Expired;
+        }
+        return CertValidity::Valid;
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum CertValidity {
+    NotYetValid,
+    Valid,
+    Expired,
+}
+
+/// What the learner achieved. Also stored as JSON in the token `extra` field.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CertContent {
+    pub course_id: String,
+    pub completion_date: U64,
+    /// Free-form grade or score, e.g. "A" or "92/100".
+    pub grade: Option<String>,
+    pub credit_hours: Option<u32>,
+    pub skills: Vec<String>,
+}
+
+impl CertContent {
+    pub fn assert_valid(&self) {
+        assert!(!self.course_id.is_empty(), "Course id is required");
+        assert!(
+            self.completion_date.0 <= env::block_timestamp(),
+            "Completion date can't be in the future"
+            );
+        if let Some(grade) = &self.grade {
+            assert!(!grade.is_empty(), "Grade can't be empty");
+        }
+        assert!(
+            self.skills.iter().all(|skill| !skill.is_empty()),
+            "Skill tags can't be empty"
+            );
+    }
+
+    fn description(&self, owner_name: &str, course_name: &str) -> String {
+        let mut description = format!("Awarded to {} for completing {}", owner_name, course_name);
+        if let Some(grade) = &self.grade {
+            description.push_str(&format!(" with grade {}", grade));
+        }
+        if let Some(credit_hours) = self.credit_hours {
+            description.push_str(&format!(", {} credit hours", credit_hours));
+        }
+        if !self.skills.is_empty() {
+            description.push_str(&format!(". Skills: {}", self.skills.join(", ")));
+        }
+        return description;
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CertInput {
+    pub owner_name: String,
+    pub owner_account: ValidAccountId,
+    /// Left empty to use the artwork of the course.
+    pub media_uri: String,
+    pub media_hash: String,
+    pub starts_at: Option<U64>,
+    pub expires_at: Option<U64>,
+    pub content: CertContent,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchIssuance {
+    pub processed: u64,
+    pub cert_ids: Vec<TokenId>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintBatch {
+    pub minted: Vec<TokenId>,
+    /// Index into the approved certificates to pass as `from_index` to
+    /// continue, `None` once every approved certificate has been visited.
+    pub next_index: Option<U64>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Revocation {
+    pub reason: String,
+    pub revoked_by: AccountId,
+    pub revoked_at: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CertStatusView {
+    pub status: CertStatus,
+    pub history: Vec<StatusTransition>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum IssuerStatus {
+    Active,
+    Suspended,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct IssuerProfile {
+    pub website: Option<String>,
+    pub logo_uri: Option<String>,
+    pub logo_hash: Option<Base64VecU8>,
+    pub contact: Option<String>,
+    pub jurisdiction: Option<String>,
+    pub description: Option<String>,
+    /// URL to an off-chain JSON file with more info about the organization.
+    pub reference: Option<String>,
+    pub reference_hash: Option<Base64VecU8>,
+}
+
+impl IssuerProfile {
+    pub fn assert_valid(&self) {
+        if let Some(logo_hash) = &self.logo_hash {
+            assert!(self.logo_uri.is_some(), "logo_hash requires logo_uri");
+            assert_sha256("logo_hash", logo_hash);
+        }
+        if let Some(reference_hash) = &self.reference_hash {
+            assert!(self.reference.is_some(), "reference_hash requires reference");
+            assert_sha256("reference_hash", reference_hash);
+        }
+    }
+}
+
+/// Issuer-defined text for the metadata of new certificates. Placeholders
+/// like `{learner}` or `{course}` are filled in per certificate, see
+/// `TEMPLATE_PLACEHOLDERS`. `None` falls back to the default text.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CertTemplate {
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+impl CertTemplate {
+    pub fn assert_valid(&self) {
+        for template in self.title.iter().chain(self.description.iter()) {
+            assert!(!template.trim().is_empty(), "Template can't be empty");
+            for placeholder in template_placeholders(template) {
+                assert!(
+                    TEMPLATE_PLACEHOLDERS.contains(&placeholder),
+                    "Unknown template placeholder {{{}}}",
+                    placeholder
+                    );
+            }
+        }
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Clone, Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Issuer {
     pub name: String,
-    pub account: ValidAccountId
+    pub account: ValidAccountId,
+    pub status: IssuerStatus,
+    pub profile: IssuerProfile,
+    pub cert_template: CertTemplate,
+}
+
+/// Certificates of an issuer by stage. `pending` counts submitted ones that are
+/// not minted yet, `minted` counts live tokens including superseded ones.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Default, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct IssuerCertCounts {
+    pub pending: u64,
+    pub minted: u64,
+    pub revoked: u64,
+}
+
+impl IssuerCertCounts {
+    fn counter(&mut self, status: CertStatus) -> Option<&mut u64> {
+        return match status {
+            CertStatus::PendingReview | CertStatus::Approved => Some(&mut self.pending),
+            CertStatus::Minted | CertStatus::Superseded => Some(&mut self.minted),
+            CertStatus::Revoked => Some(&mut self.revoked),
+            CertStatus::Draft | CertStatus::Rejected => None,
+        };
+    }
+
+    pub fn record_transition(&mut self, from: CertStatus, to: CertStatus) {
+        if let Some(count) = self.counter(from) {
+            *count -= 1;
+        }
+        if let Some(count) = self.counter(to) {
+            *count += 1;
+        }
+    }
 }
 
 #[near_bindgen]
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct Contract {
     contract_foundation: ValidAccountId,
+    pending_foundation: Option<ValidAccountId>,
     issuers: UnorderedMap<ValidAccountId, Issuer>,
 
-    certs_map: UnorderedMap<ValidAccountId, Certificate>,
+    certs_map: UnorderedMap<TokenId, Certificate>,
+    certs_per_owner: LookupMap<ValidAccountId, UnorderedSet<TokenId>>,
+    certs_per_issuer: LookupMap<AccountId, UnorderedSet<TokenId>>,
+    certs_per_status: LookupMap<CertStatus, UnorderedSet<TokenId>>,
+    issuer_cert_counts: LookupMap<AccountId, IssuerCertCounts>,
+    // Minted certificates with an expiry, ordered by it.
+    minted_by_expiry: TreeMap<(u64, TokenId), ()>,
+    next_cert_id: u64,
+    revocations: LookupMap<TokenId, Revocation>,
+    soulbound: bool,
+    storage_accounts: LookupMap<AccountId, StorageAccount>,
+    roles: LookupMap<AccountId, Vec<Role>>,
+    issuer_staff: LookupMap<AccountId, StaffDelegation>,
+    staff_per_issuer: LookupMap<AccountId, UnorderedSet<AccountId>>,
+    council: Council,
+    proposals: UnorderedMap<u64, Proposal>,
+    next_proposal_id: u64,
+    issuer_quotas: LookupMap<AccountId, IssuerQuota>,
+    issuance_usage: LookupMap<AccountId, IssuanceUsage>,
+    courses: LookupMap<AccountId, UnorderedMap<String, Course>>,
+    certs_per_course: LookupMap<String, UnorderedSet<TokenId>>,
 
     //NFT 
     nft_token: NonFungibleToken,
@@ -62,6 +386,19 @@ impl Default for Contract {
     }
 }
 
+// Gas kept in reserve for each certificate created by `new_certs_batch`.
+const GAS_PER_CERT: Gas = 5_000_000_000_000;
+// Gas kept in reserve for each token minted by a `MintCertsBatch` proposal.
+const GAS_PER_MINT: Gas = 10_000_000_000_000;
+
+const TEMPLATE_PLACEHOLDERS: [&str; 8] = [
+    "learner", "course", "course_id", "level", "issuer", "grade", "credits", "skills",
+];
+const DEFAULT_TITLE_TEMPLATE: &str = "{course} {level} — awarded to {learner}";
+
+const EVENT_STANDARD: &str = "nearcert";
+const EVENT_VERSION: &str = "1.0.0";
+
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'%3E%3Cg id='l' data-name='l'%3E%3Cpath d='M187.58,79.81l-30.1,44.69a3.2,3.2,0,0,0,4.75,4.2L191.86,103a1.2,1.2,0,0,1,2,.91v80.46a1.2,1.2,0,0,1-2.12.77L102.18,77.93A15.35,15.35,0,0,0,90.47,72.5H87.34A15.34,15.34,0,0,0,72,87.84V201.16A15.34,15.34,0,0,0,87.34,216.5h0a15.35,15.35,0,0,0,13.08-7.31l30.1-44.69a3.2,3.2,0,0,0-4.75-4.2L96.14,186a1.2,1.2,0,0,1-2-.91V104.61a1.2,1.2,0,0,1,2.12-.77l89.55,107.23a15.35,15.35,0,0,0,11.71,5.43h3.13A15.34,15.34,0,0,0,216,201.16V87.84A15.34,15.34,0,0,0,200.66,72.5h0A15.35,15.35,0,0,0,187.58,79.81Z'/%3E%3C/g%3E%3C/svg%3E";
 
 #[near_bindgen]
@@ -82,33 +419,136 @@ impl Contract {
 
         let signer = ValidAccountId::try_from(env::predecessor_account_id().clone()).unwrap();
 
-        Contract {
+        let mut this = Contract {
             contract_foundation: signer.clone(),
+            pending_foundation: None,
             issuers: UnorderedMap::new(b"i".to_vec()),
             certs_map: UnorderedMap::new(b"cert".to_vec()),
+            certs_per_owner: LookupMap::new(StorageKey::CertsPerOwner),
+            certs_per_issuer: LookupMap::new(StorageKey::CertsPerIssuer),
+            certs_per_status: LookupMap::new(StorageKey::CertsPerStatus),
+            issuer_cert_counts: LookupMap::new(StorageKey::IssuerCertCounts),
+            minted_by_expiry: TreeMap::new(StorageKey::MintedByExpiry),
+            next_cert_id: 0,
+            revocations: LookupMap::new(StorageKey::Revocations),
+            soulbound: true,
+            storage_accounts: LookupMap::new(StorageKey::StorageAccounts),
+            roles: LookupMap::new(StorageKey::Roles),
+            issuer_staff: LookupMap::new(StorageKey::IssuerStaff),
+            staff_per_issuer: LookupMap::new(StorageKey::StaffPerIssuer),
+            council: Council::new(vec![signer.clone()], 1),
+            proposals: UnorderedMap::new(StorageKey::Proposals),
+            next_proposal_id: 0,
+            issuer_quotas: LookupMap::new(StorageKey::IssuerQuotas),
+            issuance_usage: LookupMap::new(StorageKey::IssuanceUsage),
+            courses: LookupMap::new(StorageKey::Courses),
+            certs_per_course: LookupMap::new(StorageKey::CertsPerCourse),
             nft_token: NonFungibleToken::new(
                 StorageKey::NonFungibleToken,
-                signer,
+                signer.clone(),
                 Some(StorageKey::TokenMetadata),
                 Some(StorageKey::Enumeration),
                 Some(StorageKey::Approval),
                 ),
             metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
-        }
+        };
+        this.internal_grant_role(signer.as_ref(), Role::FoundationAdmin);
+        this.internal_grant_role(signer.as_ref(), Role::Reviewer);
+        this
     }
 
-    pub fn new_issuer(&mut self, issuer: ValidAccountId, issuer_name: String) -> bool {
-        self.only_owner();
+    /// First step of moving the foundation to another account, e.g. a DAO. The
+    /// proposal only takes effect once `new_foundation` calls `accept_foundation`.
+    pub fn propose_foundation(&mut self, new_foundation: ValidAccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.contract_foundation.to_string(),
+            "Only the contract foundation can call this fn"
+            );
+        assert_ne!(new_foundation, self.contract_foundation, "Account is already the foundation");
 
-        if !self.issuers.get(&issuer).is_some() {
-            let _issuer = Issuer {
-                name: issuer_name,
-                account: issuer.clone()
-            };
-            self.issuers.insert(&issuer, &_issuer);
-            return true;   
-        }
-        return false;
+        self.pending_foundation = Some(new_foundation.clone());
+        log_event("foundation_proposed", json!({
+            "foundation": self.contract_foundation,
+            "proposed_foundation": new_foundation,
+        }));
+    }
+
+    /// Completes the transfer: roles, NFT ownership and the old foundation's
+    /// council seat move to the caller.
+    pub fn accept_foundation(&mut self) {
+        let new_foundation = ValidAccountId::try_from(env::predecessor_account_id()).unwrap();
+        assert_eq!(
+            self.pending_foundation.as_ref(),
+            Some(&new_foundation),
+            "Only the proposed foundation can call this fn"
+            );
+
+        let old_foundation = self.contract_foundation.clone();
+        self.internal_revoke_role(old_foundation.as_ref(), Role::FoundationAdmin);
+        self.internal_revoke_role(old_foundation.as_ref(), Role::Reviewer);
+        self.internal_grant_role(new_foundation.as_ref(), Role::FoundationAdmin);
+        self.internal_grant_role(new_foundation.as_ref(), Role::Reviewer);
+        self.council.replace_member(&old_foundation, &new_foundation);
+
+        self.contract_foundation = new_foundation.clone();
+        self.nft_token.owner_id = new_foundation.to_string();
+        self.pending_foundation = None;
+
+        log_event("foundation_transferred", json!({
+            "old_foundation": old_foundation,
+            "new_foundation": new_foundation,
+        }));
+    }
+
+    /// Any single foundation admin can suspend an issuer, so a compromised
+    /// issuer key is stopped right away. Suspension only blocks new work and
+    /// lifting it takes a `ReinstateIssuer` council proposal.
+    pub fn suspend_issuer(&mut self, issuer: ValidAccountId) -> Issuer {
+        self.assert_role(Role::FoundationAdmin);
+        return self.internal_set_issuer_status(&issuer, IssuerStatus::Suspended);
+    }
+
+    pub fn update_issuer(&mut self, issuer: ValidAccountId, issuer_name: String) -> Issuer {
+        self.assert_role(Role::FoundationAdmin);
+        assert!(!issuer_name.is_empty(), "Issuer name is required");
+
+        let mut _issuer = self.internal_get_issuer(&issuer);
+        _issuer.name = issuer_name;
+        self.issuers.insert(&issuer, &_issuer);
+        return _issuer;
+    }
+
+    /// Lets an issuer describe its organization for verifiers. The whole
+    /// profile is replaced on each call.
+    pub fn update_issuer_profile(&mut self, profile: IssuerProfile) -> IssuerProfile {
+        let account = self.assert_role(Role::Issuer);
+        self.assert_active_issuer(&account);
+        profile.assert_valid();
+        let initial_storage_usage = env::storage_usage();
+
+        let mut issuer = self.internal_get_issuer(&account);
+        issuer.profile = profile;
+        self.issuers.insert(&account, &issuer);
+
+        self.internal_charge_storage(&account.to_string(), initial_storage_usage);
+        return issuer.profile;
+    }
+
+    /// Sets how the title and description of the issuer's new certificates
+    /// are worded. Existing certificates keep their text.
+    pub fn set_cert_template(&mut self, template: CertTemplate) -> CertTemplate {
+        let account = self.assert_role(Role::Issuer);
+        self.assert_active_issuer(&account);
+        template.assert_valid();
+        let initial_storage_usage = env::storage_usage();
+
+        let mut issuer = self.internal_get_issuer(&account);
+        issuer.cert_template = template;
+        self.issuers.insert(&account, &issuer);
+
+        self.internal_charge_storage(&account.to_string(), initial_storage_usage);
+        return issuer.cert_template;
     }
 
     pub fn new_cert(
@@ -117,106 +557,873 @@ impl Contract {
         _owner_account: ValidAccountId, 
         _media_uri: String,
         _media_hash: String,
+        _starts_at: Option<U64>,
+        _expires_at: Option<U64>,
+        _content: CertContent,
         ) -> Certificate {
-        self.only_issuer();
+        let issuer = self.assert_acting_issuer(StaffScope::Draft);
 
-        let predecessor = env::predecessor_account_id();
-        let receiver_id = ValidAccountId::try_from(predecessor.clone()).unwrap();
+        let input = CertInput {
+            owner_name: _owner_name,
+            owner_account: _owner_account,
+            media_uri: _media_uri,
+            media_hash: _media_hash,
+            starts_at: _starts_at,
+            expires_at: _expires_at,
+            content: _content,
+        };
+        let input = self.internal_apply_course(&issuer, input);
+        assert_cert_input(&input);
 
-        let creator = self.issuers.get(&receiver_id);
+        let initial_storage_usage = env::storage_usage();
+        let cert = self.internal_new_cert(&issuer, input);
+        self.internal_charge_storage(&issuer.to_string(), initial_storage_usage);
+        return cert;
+    }
+
+    /// Creates draft certificates for a whole cohort. Every entry is validated
+    /// before anything is written; creation then stops early when prepaid gas
+    /// runs low, so callers resubmit the entries after `processed`.
+    pub fn new_certs_batch(&mut self, certs: Vec<CertInput>) -> BatchIssuance {
+        let issuer = self.assert_acting_issuer(StaffScope::Draft);
+        assert!(!certs.is_empty(), "Batch is empty");
+
+        let certs: Vec<CertInput> = certs
+            .into_iter()
+            .map(|input| self.internal_apply_course(&issuer, input))
+            .collect();
+        for input in certs.iter() {
+            assert_cert_input(input);
+        }
+        self.assert_quota_allows(&issuer, certs.len() as u64);
+
+        let initial_storage_usage = env::storage_usage();
+        let mut cert_ids = vec![];
+        for input in certs {
+            if env::used_gas() + GAS_PER_CERT > env::prepaid_gas() {
+                break;
+            }
+            cert_ids.push(self.internal_new_cert(&issuer, input).id);
+        }
+        self.internal_charge_storage(&issuer.to_string(), initial_storage_usage);
+
+        return BatchIssuance {
+            processed: cert_ids.len() as u64,
+            cert_ids,
+        };
+    }
+
+    /// Issues a new draft certificate continuing `cert_id` for the same learner
+    /// with a later expiry. The predecessor stays valid until the renewal is
+    /// minted; it is then marked superseded, not revoked, and both certificates
+    /// link to each other.
+    pub fn renew_cert(
+        &mut self,
+        cert_id: TokenId,
+        _starts_at: Option<U64>,
+        _expires_at: U64,
+        ) -> Certificate {
+        let issuer = self.assert_acting_issuer(StaffScope::Issue);
+        let initial_storage_usage = env::storage_usage();
+
+        let previous = self.internal_get_cert(&cert_id);
+        assert_cert_issuer(&previous, &issuer);
+        assert_eq!(previous.status, CertStatus::Minted, "Only minted certificates can be renewed");
+        if let Some(previous_expires_at) = previous.expires_at() {
+            assert!(
+                _expires_at.0 > previous_expires_at,
+                "Renewal must extend the validity window"
+                );
+        }
+        let starts_at = _starts_at.or(previous.expires_at().map(U64));
+        assert_validity_window(starts_at, Some(_expires_at));
+
+        self.internal_consume_quota(&issuer);
+        let creator = self.internal_get_issuer(&issuer);
+        let renewed_id = self.internal_next_cert_id();
+        let mut metadata = previous.metadata.clone();
+        metadata.issued_at = Some(env::block_timestamp().to_string());
+        metadata.starts_at = starts_at.map(|t| t.0.to_string());
+        metadata.expires_at = Some(_expires_at.0.to_string());
+        metadata.updated_at = None;
+
+        let renewed = Certificate {
+            id: renewed_id.clone(),
+            owner_name: previous.owner_name.clone(),
+            issuer_account: creator.account,
+            issuer_name: creator.name,
+            issuer_status: creator.status,
+            authored_by: env::predecessor_account_id(),
+            status: CertStatus::Draft,
+            history: vec![],
+            content: previous.content.clone(),
+            metadata,
+            owner_account: previous.owner_account.clone(),
+            renewal_of: Some(cert_id),
+            superseded_by: None,
+        };
+        self.internal_add_cert(&renewed);
+
+        self.internal_charge_storage(&issuer.to_string(), initial_storage_usage);
+        return renewed;
+    }
+
+    pub fn submit_cert(&mut self, cert_id: TokenId) -> CertStatus {
+        let issuer = self.assert_acting_issuer(StaffScope::Issue);
+        let initial_storage_usage = env::storage_usage();
+
+        let mut cert = self.internal_get_cert(&cert_id);
+        assert_cert_issuer(&cert, &issuer);
+        self.internal_set_status(&mut cert, CertStatus::PendingReview, None);
+
+        self.internal_charge_storage(&cert.issuer_account.to_string(), initial_storage_usage);
+        return cert.status;
+    }
+
+    pub fn approve_cert(&mut self, cert_id: TokenId) -> CertStatus {
+        self.assert_role(Role::Reviewer);
+        let initial_storage_usage = env::storage_usage();
+
+        let mut cert = self.internal_get_cert(&cert_id);
+        self.internal_set_status(&mut cert, CertStatus::Approved, None);
+
+        self.internal_charge_storage(&cert.issuer_account.to_string(), initial_storage_usage);
+        return cert.status;
+    }
+
+    pub fn reject_cert(&mut self, cert_id: TokenId, reason: String) -> CertStatus {
+        self.assert_role(Role::Reviewer);
+        assert!(!reason.is_empty(), "Rejection reason is required");
+
+        let initial_storage_usage = env::storage_usage();
+
+        let mut cert = self.internal_get_cert(&cert_id);
+        self.internal_set_status(&mut cert, CertStatus::Rejected, Some(reason));
+
+        self.internal_charge_storage(&cert.issuer_account.to_string(), initial_storage_usage);
+        return cert.status;
+    }
+
+    /// Revokes a certificate on behalf of its issuer. The foundation revokes
+    /// through a `RevokeCert` council proposal instead.
+    pub fn revoke_cert(&mut self, cert_id: TokenId, reason: String) -> Revocation {
+        let issuer = self.assert_acting_issuer(StaffScope::Revoke);
+        let cert = self.internal_get_cert(&cert_id);
+        assert_cert_issuer(&cert, &issuer);
+
+        self.internal_burn_revoked(&cert);
+        let initial_storage_usage = env::storage_usage();
+        let revocation = self.internal_revoke_cert(cert, reason);
+        self.internal_charge_storage(&issuer.to_string(), initial_storage_usage);
+        return revocation;
+    }
+
+    //View function
+    pub fn get_foundation(&self) -> ValidAccountId {
+        return self.contract_foundation.clone();
+    }
+
+    pub fn pending_foundation(&self) -> Option<ValidAccountId> {
+        return self.pending_foundation.clone();
+    }
+
+    pub fn get_cert(&self, cert_id: TokenId) -> Option<Certificate> {
+        return self.certs_map.get(&cert_id);
+    }
+
+    pub fn cert_status(&self, cert_id: TokenId) -> Option<CertStatusView> {
+        return self.certs_map.get(&cert_id).map(|cert| CertStatusView {
+            status: cert.status,
+            history: cert.history,
+        });
+    }
+
+    pub fn cert_validity(&self, token_id: TokenId) -> Option<CertValidity> {
+        return self
+            .certs_map
+            .get(&token_id)
+            .map(|cert| cert.validity_at(env::block_timestamp()));
+    }
+
+    pub fn is_revoked(&self, cert_id: TokenId) -> bool {
+        return self.revocations.contains_key(&cert_id);
+    }
+
+    pub fn revocation_of(&self, cert_id: TokenId) -> Option<Revocation> {
+        return self.revocations.get(&cert_id);
+    }
+
+    pub fn issuer_profile(&self, account: ValidAccountId) -> Option<IssuerProfile> {
+        return self.issuers.get(&account).map(|issuer| issuer.profile);
+    }
+
+    pub fn is_soulbound(&self) -> bool {
+        return self.soulbound;
+    }
+
+    //Helper function
+    fn internal_new_issuer(&mut self, issuer: ValidAccountId, issuer_name: String) -> bool {
+        if self.issuers.get(&issuer).is_some() {
+            return false;
+        }
+
+        let _issuer = Issuer {
+            name: issuer_name,
+            account: issuer.clone(),
+            status: IssuerStatus::Active,
+            profile: IssuerProfile::default(),
+            cert_template: CertTemplate::default(),
+        };
+        self.issuers.insert(&issuer, &_issuer);
+        self.internal_grant_role(issuer.as_ref(), Role::Issuer);
+        if self.issuer_cert_counts.get(issuer.as_ref()).is_none() {
+            self.issuer_cert_counts.insert(issuer.as_ref(), &IssuerCertCounts::default());
+        }
+        return true;
+    }
+
+    fn internal_remove_issuer(&mut self, issuer: ValidAccountId) -> bool {
+        let removed = self.issuers.remove(&issuer).is_some();
+        if removed {
+            self.internal_revoke_role(issuer.as_ref(), Role::Issuer);
+            let initial_storage_usage = env::storage_usage();
+            self.internal_remove_all_delegates(&issuer);
+            if self.storage_accounts.get(issuer.as_ref()).is_some() {
+                self.internal_charge_storage(issuer.as_ref(), initial_storage_usage);
+            }
+            env::log(format!("Removed issuer {}", issuer).as_bytes());
+        }
+        return removed;
+    }
+
+    // Moves a minted certificate to another account of the same learner, e.g.
+    // after a lost key. This is the only transfer allowed in soulbound mode.
+    fn internal_recover_cert(&mut self, cert_id: TokenId, new_owner: ValidAccountId) {
+        let cert = self.internal_get_cert(&cert_id);
+        assert_eq!(cert.status, CertStatus::Minted, "Only minted certificates can be recovered");
+
+        let old_owner = self.nft_token.owner_by_id.get(&cert_id).expect("Token not found");
+        assert_ne!(old_owner, new_owner.to_string(), "Certificate is already owned by this account");
+
+        self.nft_token.internal_transfer_unguarded(&cert_id, &old_owner, &new_owner.to_string());
+        if let Some(approvals_by_id) = &mut self.nft_token.approvals_by_id {
+            approvals_by_id.remove(&cert_id);
+        }
+        self.internal_sync_cert_owner(&cert_id);
+
+        env::log(format!("Recovered certificate {} from {} to {}", cert_id, old_owner, new_owner).as_bytes());
+    }
+
+    // Mints the certificate directly to its `owner_account`. Storage is paid
+    // from the issuing issuer's storage balance.
+    fn internal_mint_cert(&mut self, cert_id: TokenId) -> Token {
+        let initial_storage_usage = env::storage_usage();
+
+        let mut cert = self.internal_get_cert(&cert_id);
+        self.assert_active_issuer(&cert.issuer_account);
+        self.internal_set_status(&mut cert, CertStatus::Minted, None);
+        let token = self.internal_mint(&cert);
+        self.internal_supersede_renewed(&cert);
+
+        self.internal_charge_storage(&cert.issuer_account.to_string(), initial_storage_usage);
+        return token;
+    }
+
+    // Mints approved certificates straight to their owners, walking the
+    // Approved status index from `from_index`. Minted certificates leave the
+    // index, so only skipped ones stay behind the cursor. Stops before prepaid
+    // gas runs out or after `limit` mints and returns the index to resume from.
+    fn internal_mint_certs_batch(&mut self, from_index: Option<U64>, limit: Option<u64>) -> MintBatch {
+        let limit = limit.unwrap_or(u64::MAX);
+        let mut index = from_index.map_or(0, |i| i.0);
+        let mut minted = vec![];
+        while index < self.internal_approved_count() && (minted.len() as u64) < limit {
+            if env::used_gas() + GAS_PER_MINT > env::prepaid_gas() {
+                break;
+            }
+
+            let approved = self.certs_per_status.get(&CertStatus::Approved).unwrap();
+            let cert_id = approved.as_vector().get(index).unwrap();
+            let mut cert = self.internal_get_cert(&cert_id);
+            // Certificates of removed or suspended issuers, and of issuers that
+            // can't pay for the token, are skipped rather than reverting the
+            // whole batch.
+            let issuer = cert.issuer_account.to_string();
+            if self.is_active_issuer(&cert.issuer_account) && self.internal_can_pay_mint(&issuer, &cert) {
+                let initial_storage_usage = env::storage_usage();
+                self.internal_set_status(&mut cert, CertStatus::Minted, None);
+                self.internal_mint(&cert);
+                self.internal_supersede_renewed(&cert);
+                self.internal_charge_storage(&issuer, initial_storage_usage);
+                minted.push(cert_id);
+            } else {
+                index += 1;
+            }
+        }
+
+        return MintBatch {
+            minted,
+            next_index: if index < self.internal_approved_count() { Some(U64(index)) } else { None },
+        };
+    }
+
+    fn internal_approved_count(&self) -> u64 {
+        return self.certs_per_status.get(&CertStatus::Approved).map_or(0, |cert_ids| cert_ids.len());
+    }
+
+    // Records the revocation, which stays queryable through `revocation_of`.
+    // Its storage is settled by the caller: the issuer's balance when the
+    // issuer revokes, the proposal deposit when the foundation does.
+    fn internal_revoke_cert(&mut self, mut cert: Certificate, reason: String) -> Revocation {
+        assert!(!reason.is_empty(), "Revocation reason is required");
+        let cert_id = cert.id.clone();
+
+        self.internal_set_status(&mut cert, CertStatus::Revoked, Some(reason.clone()));
+
+        let revocation = Revocation {
+            reason,
+            revoked_by: env::predecessor_account_id(),
+            revoked_at: U64(env::block_timestamp()),
+        };
+        self.revocations.insert(&cert_id, &revocation);
+        return revocation;
+    }
+
+    fn internal_new_cert(&mut self, issuer: &ValidAccountId, input: CertInput) -> Certificate {
+        self.internal_consume_quota(issuer);
+        let creator = self.internal_get_issuer(issuer);
+        let course = self.internal_get_course(issuer, &input.content.course_id);
+
+        let values = [
+            ("learner", input.owner_name.clone()),
+            ("course", course.name.clone()),
+            ("course_id", course.id.clone()),
+            ("level", course.level.clone().unwrap_or_default()),
+            ("issuer", creator.name.clone()),
+            ("grade", input.content.grade.clone().unwrap_or_default()),
+            ("credits", input.content.credit_hours.map_or(String::new(), |c| c.to_string())),
+            ("skills", input.content.skills.join(", ")),
+        ];
+        let template = &creator.cert_template;
+        let title = render_template(template.title.as_deref().unwrap_or(DEFAULT_TITLE_TEMPLATE), &values);
+        let description = match &template.description {
+            Some(description) => render_template(description, &values),
+            None => input.content.description(&input.owner_name, &course.name),
+        };
 
         let metadata = TokenMetadata {
-            title: Some("L1 Certificate".into()),
-            description: Some("".into()),
-            media: Some(_media_uri.into()),
-            media_hash: None,
+            title: Some(title),
+            description: Some(description),
+            media: Some(input.media_uri),
+            media_hash: Some(decode_media_hash(&input.media_hash)),
             copies: Some(1u64),
             issued_at: Some(env::block_timestamp().to_string()),
-            expires_at: None,
-            starts_at: None,
+            expires_at: input.expires_at.map(|t| t.0.to_string()),
+            starts_at: input.starts_at.map(|t| t.0.to_string()),
             updated_at: None,
-            extra: None,
+            extra: Some(json!(input.content).to_string()),
             reference: None,
             reference_hash: None,
         };
 
+        let cert_id = self.internal_next_cert_id();
+
         let cert = Certificate {
-            owner_name: _owner_name,
-            issuer_account: creator.unwrap().account,
-            is_approved: false,
-            metadata: metadata,
-            owner_account: _owner_account.clone() 
+            id: cert_id,
+            owner_name: input.owner_name,
+            issuer_account: creator.account,
+            issuer_name: creator.name,
+            issuer_status: creator.status,
+            authored_by: env::predecessor_account_id(),
+            status: CertStatus::Draft,
+            history: vec![],
+            content: input.content,
+            metadata,
+            owner_account: input.owner_account,
+            renewal_of: None,
+            superseded_by: None,
         };
 
-        self.certs_map.insert(&_owner_account, &cert);
+        self.internal_add_cert(&cert);
         return cert;
     }
 
-    // pub fn approve(&mut self, account: ValidAccountId) -> bool {
-    //     assert!(
-    //         self.certs_map.get(&account).is_some(),
-    //         "This account doesn't have any cert"
-    //         );
-    //     self.only_owner();
+    fn internal_next_cert_id(&mut self) -> TokenId {
+        let cert_id = self.next_cert_id.to_string();
+        self.next_cert_id += 1;
+        return cert_id;
+    }
+
+    fn internal_get_cert(&self, cert_id: &TokenId) -> Certificate {
+        return self.certs_map.get(cert_id).expect("Certificate not found");
+    }
 
-    //     let mut cert = self.certs_map.get(&account).unwrap();
-    //     cert.is_approved = true;
-    //     return true;
-    // }
+    fn internal_set_status(&mut self, cert: &mut Certificate, to: CertStatus, reason: Option<String>) {
+        assert!(
+            cert.status.can_transition_to(to),
+            "Invalid certificate status transition from {:?} to {:?}",
+            cert.status,
+            to
+            );
 
-    #[payable]
-    pub fn mint_cert(&mut self, account: ValidAccountId) -> Token {
-        self.only_owner();
+        cert.history.push(StatusTransition {
+            from: cert.status,
+            to,
+            actor: env::predecessor_account_id(),
+            timestamp: U64(env::block_timestamp()),
+            reason,
+        });
+        self.internal_remove_cert_from_status(cert.status, &cert.id);
+        self.internal_add_cert_to_status(to, &cert.id);
+        if let Some(expires_at) = cert.expires_at() {
+            if cert.status == CertStatus::Minted {
+                self.minted_by_expiry.remove(&(expires_at, cert.id.clone()));
+            }
+            if to == CertStatus::Minted {
+                self.minted_by_expiry.insert(&(expires_at, cert.id.clone()), &());
+            }
+        }
+
+        let issuer = cert.issuer_account.to_string();
+        let mut counts = self.issuer_cert_counts.get(&issuer).unwrap_or_default();
+        counts.record_transition(cert.status, to);
+        self.issuer_cert_counts.insert(&issuer, &counts);
+
+        cert.status = to;
+        self.certs_map.insert(&cert.id, cert);
+    }
+
+    // Stores a new certificate and adds it to the owner, issuer, status and
+    // course indexes used by the listing views.
+    fn internal_add_cert(&mut self, cert: &Certificate) {
+        self.certs_map.insert(&cert.id, cert);
+        self.internal_add_cert_to_owner(&cert.owner_account, &cert.id);
+        self.internal_add_cert_to_status(cert.status, &cert.id);
+
+        let issuer = cert.issuer_account.to_string();
+        let mut cert_ids = self.certs_per_issuer.get(&issuer).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::CertsPerIssuerInner {
+                account_hash: env::sha256(issuer.as_bytes()),
+            })
+        });
+        cert_ids.insert(&cert.id);
+        self.certs_per_issuer.insert(&issuer, &cert_ids);
+        self.internal_add_cert_to_course(cert);
+    }
+
+    fn internal_add_cert_to_status(&mut self, status: CertStatus, cert_id: &TokenId) {
+        let mut cert_ids = self
+            .certs_per_status
+            .get(&status)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKey::CertsPerStatusInner { status }));
+        cert_ids.insert(cert_id);
+        self.certs_per_status.insert(&status, &cert_ids);
+    }
+
+    fn internal_remove_cert_from_status(&mut self, status: CertStatus, cert_id: &TokenId) {
+        if let Some(mut cert_ids) = self.certs_per_status.get(&status) {
+            cert_ids.remove(cert_id);
+            self.certs_per_status.insert(&status, &cert_ids);
+        }
+    }
+
+    fn internal_add_cert_to_owner(&mut self, account: &ValidAccountId, cert_id: &TokenId) {
+        let mut cert_ids = self.certs_per_owner.get(account).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::CertsPerOwnerInner {
+                account_hash: env::sha256(account.to_string().as_bytes()),
+            })
+        });
+        cert_ids.insert(cert_id);
+        self.certs_per_owner.insert(account, &cert_ids);
+    }
+
+    // Same bookkeeping as `NonFungibleToken::mint`, minus its owner check and
+    // per-token refund, so several tokens can be minted in one call.
+    fn internal_mint(&mut self, cert: &Certificate) -> Token {
+        let token_id = cert.id.clone();
+        let owner_id: AccountId = cert.owner_account.to_string();
 
         assert!(
-            self.certs_map.get(&account).is_some(),
-            "This account doesn't have any cert"
+            self.nft_token.owner_by_id.insert(&token_id, &owner_id).is_none(),
+            "token_id must be unique"
             );
 
-        let cert = self.certs_map.get(&account).unwrap();
-        let token = self.nft_token.mint(cert.owner_account.to_string(), account, Some(cert.metadata));
+        if let Some(token_metadata_by_id) = &mut self.nft_token.token_metadata_by_id {
+            token_metadata_by_id.insert(&token_id, &cert.metadata);
+        }
+        if let Some(tokens_per_owner) = &mut self.nft_token.tokens_per_owner {
+            let mut token_ids = tokens_per_owner.get(&owner_id).unwrap_or_else(|| {
+                UnorderedSet::new(StorageKey::TokensPerOwnerInner {
+                    account_hash: env::sha256(owner_id.as_bytes()),
+                })
+            });
+            token_ids.insert(&token_id);
+            tokens_per_owner.insert(&owner_id, &token_ids);
+        }
+        let approved_account_ids = if let Some(approvals_by_id) = &mut self.nft_token.approvals_by_id {
+            let approved_account_ids = HashMap::new();
+            approvals_by_id.insert(&token_id, &approved_account_ids);
+            Some(approved_account_ids)
+        } else {
+            None
+        };
 
-        return token;
+        return Token {
+            token_id,
+            owner_id,
+            metadata: Some(cert.metadata.clone()),
+            approved_account_ids,
+        };
     }
 
-    #[payable]
-    pub fn transfer_to_owner(&mut self, account: ValidAccountId) {
-        self.only_owner();
-        self.nft_transfer(account.clone(), account.clone().to_string(), None, None);
+    fn internal_burn(&mut self, token_id: &TokenId) {
+        let owner_id = self
+            .nft_token
+            .owner_by_id
+            .remove(token_id)
+            .expect("Token not found");
+
+        if let Some(token_metadata_by_id) = &mut self.nft_token.token_metadata_by_id {
+            token_metadata_by_id.remove(token_id);
+        }
+        if let Some(tokens_per_owner) = &mut self.nft_token.tokens_per_owner {
+            let mut token_ids = tokens_per_owner.get(&owner_id).unwrap();
+            token_ids.remove(token_id);
+            if token_ids.is_empty() {
+                tokens_per_owner.remove(&owner_id);
+            } else {
+                tokens_per_owner.insert(&owner_id, &token_ids);
+            }
+        }
+        if let Some(approvals_by_id) = &mut self.nft_token.approvals_by_id {
+            approvals_by_id.remove(token_id);
+        }
+        if let Some(next_approval_id_by_id) = &mut self.nft_token.next_approval_id_by_id {
+            next_approval_id_by_id.remove(token_id);
+        }
     }
 
-    //View function
-    pub fn cert_lists(&self) -> Vec<(ValidAccountId, Certificate)> {
+    fn internal_remove_cert_from_owner(&mut self, account: &ValidAccountId, cert_id: &TokenId) {
+        if let Some(mut cert_ids) = self.certs_per_owner.get(account) {
+            cert_ids.remove(cert_id);
+            if cert_ids.is_empty() {
+                self.certs_per_owner.remove(account);
+            } else {
+                self.certs_per_owner.insert(account, &cert_ids);
+            }
+        }
+    }
+
+    // A minted token is burned so it disappears from wallets. The storage it
+    // frees goes back to the issuer that paid for it, if it still has a balance.
+    fn internal_burn_revoked(&mut self, cert: &Certificate) {
+        if self.nft_token.owner_by_id.get(&cert.id).is_none() {
+            return;
+        }
+        let initial_storage_usage = env::storage_usage();
+        self.internal_burn(&cert.id);
+
+        let issuer = cert.issuer_account.to_string();
+        if self.storage_accounts.get(&issuer).is_some() {
+            self.internal_charge_storage(&issuer, initial_storage_usage);
+        }
+    }
+
+    // Once a renewal is minted its predecessor is superseded, unless it was
+    // revoked or superseded by another renewal in the meantime.
+    fn internal_supersede_renewed(&mut self, renewed: &Certificate) {
+        let previous_id = match &renewed.renewal_of {
+            Some(previous_id) => previous_id,
+            None => return,
+        };
+        let mut previous = self.internal_get_cert(previous_id);
+        if previous.status != CertStatus::Minted {
+            return;
+        }
+        previous.superseded_by = Some(renewed.id.clone());
+        self.internal_set_status(&mut previous, CertStatus::Superseded, Some(format!("Renewed by {}", renewed.id)));
+    }
+
+    // Makes the certificate and the owner index follow the current holder of
+    // its token after a transfer.
+    fn internal_sync_cert_owner(&mut self, token_id: &TokenId) {
+        let owner_id = match self.nft_token.owner_by_id.get(token_id) {
+            Some(owner_id) => owner_id,
+            None => return,
+        };
+        let mut cert = self.internal_get_cert(token_id);
+        if cert.owner_account.to_string() == owner_id {
+            return;
+        }
+
+        let new_owner = ValidAccountId::try_from(owner_id).unwrap();
+        self.internal_remove_cert_from_owner(&cert.owner_account, token_id);
+        self.internal_add_cert_to_owner(&new_owner, token_id);
+        cert.owner_account = new_owner;
+        self.certs_map.insert(token_id, &cert);
+    }
+
+    fn assert_transferable(&self) {
+        assert!(!self.soulbound, "Certificates are soulbound and cannot be transferred");
+    }
+
+    fn internal_get_issuer(&self, issuer: &ValidAccountId) -> Issuer {
+        return self.issuers.get(issuer).expect("Issuer not found");
+    }
+
+    fn is_active_issuer(&self, issuer: &ValidAccountId) -> bool {
         return self
-            .certs_map
-            .iter()
-            .collect();
+            .issuers
+            .get(issuer)
+            .is_some_and(|issuer| issuer.status == IssuerStatus::Active);
     }
 
-    //Helper function
-    fn only_owner(&self) {
-        let predecessor = env::predecessor_account_id();
-        let receiver_id = ValidAccountId::try_from(predecessor.clone()).unwrap();
+    fn internal_set_issuer_status(&mut self, issuer: &ValidAccountId, status: IssuerStatus) -> Issuer {
+        let mut _issuer = self.internal_get_issuer(issuer);
+        assert_ne!(_issuer.status, status, "Issuer is already {:?}", status);
 
-        assert_eq!(
-            &receiver_id,
-            &self.contract_foundation,
-            "Only contract owner can call this fn"
-            );
+        _issuer.status = status;
+        self.issuers.insert(issuer, &_issuer);
+        env::log(format!("Issuer {} is now {:?}", issuer, status).as_bytes());
+        return _issuer;
     }
+}
 
-    fn only_issuer(&self) {
-        let signer = ValidAccountId::try_from(env::predecessor_account_id().clone()).unwrap();
+// Logs an event in the NEP-297 `EVENT_JSON:` format so indexers can pick it up.
+fn log_event(event: &str, data: near_sdk::serde_json::Value) {
+    let event = json!({
+        "standard": EVENT_STANDARD,
+        "version": EVENT_VERSION,
+        "event": event,
+        "data": [data],
+    });
+    env::log(format!("EVENT_JSON:{}", event).as_bytes());
+}
+
+fn assert_cert_issuer(cert: &Certificate, issuer: &ValidAccountId) {
+    assert_eq!(
+        &cert.issuer_account,
+        issuer,
+        "Only the issuer of this certificate can call this fn"
+        );
+}
+
+fn parse_timestamp(value: &Option<String>) -> Option<u64> {
+    return value.as_ref().and_then(|v| v.parse::<u64>().ok());
+}
+
+fn refund_deposit(storage_used: u64) {
+    let required_cost = env::storage_byte_cost() * Balance::from(storage_used);
+    let attached_deposit = env::attached_deposit();
+
+    assert!(
+        required_cost <= attached_deposit,
+        "Attached deposit of {} yoctoNEAR does not cover the {} yoctoNEAR needed for {} bytes of storage",
+        attached_deposit,
+        required_cost,
+        storage_used
+        );
+
+    let refund = attached_deposit - required_cost;
+    if refund > 1 {
+        Promise::new(env::predecessor_account_id()).transfer(refund);
+    }
+}
+
+fn assert_cert_input(input: &CertInput) {
+    assert!(!input.owner_name.is_empty(), "Owner name is required");
+    assert!(!input.media_uri.is_empty(), "Media URI is required");
+    decode_media_hash(&input.media_hash);
+    input.content.assert_valid();
+    assert_validity_window(input.starts_at, input.expires_at);
+}
+
+// `media_hash` is the base64 encoded SHA-256 digest of the artwork at
+// `media_uri`, as NEP-177 expects.
+fn decode_media_hash(media_hash: &str) -> Base64VecU8 {
+    let digest = near_sdk::base64::decode(media_hash)
+        .unwrap_or_else(|_| env::panic(b"media_hash must be base64 encoded"));
+    let digest = Base64VecU8(digest);
+    assert_sha256("media_hash", &digest);
+    return digest;
+}
+
+// Names between braces, e.g. `learner` for "Awarded to {learner}".
+fn template_placeholders(template: &str) -> Vec<&str> {
+    let mut placeholders = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => env::panic(b"Template has an unclosed placeholder"),
+        };
+        placeholders.push(&rest[start + 1..end]);
+        rest = &rest[end + 1..];
+    }
+    return placeholders;
+}
+
+// Fills placeholders in a single pass, so values are never expanded again.
+// A placeholder without a value, e.g. a course without a level, renders empty
+// and the spaces around it are collapsed; line breaks are kept.
+fn render_template(template: &str, values: &[(&str, String)]) -> String {
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => env::panic(b"Template has an unclosed placeholder"),
+        };
+        let name = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        let value = values
+            .iter()
+            .find(|(placeholder, _)| *placeholder == name)
+            .map_or("", |(_, value)| value.as_str());
+        if value.is_empty() {
+            text.truncate(text.trim_end_matches(' ').len());
+            if text.is_empty() || text.ends_with('\n') {
+                rest = rest.trim_start_matches(' ');
+            }
+        } else {
+            text.push_str(value);
+        }
+    }
+    text.push_str(rest);
+    return text;
+}
 
+fn assert_validity_window(starts_at: Option<U64>, expires_at: Option<U64>) {
+    if let Some(expires_at) = expires_at {
         assert!(
-            self.issuers.get(&signer).is_some(),
-            "Only called by issuers"
+            expires_at.0 > env::block_timestamp(),
+            "Certificate expiry must be in the future"
             );
+        if let Some(starts_at) = starts_at {
+            assert!(
+                starts_at.0 < expires_at.0,
+                "Certificate validity must start before it expires"
+                );
+        }
+    }
+}
+
+fn assert_sha256(field: &str, hash: &Base64VecU8) {
+    assert_eq!(
+        hash.0.len(),
+        32,
+        "{} must be a SHA-256 digest of 32 bytes, got {} bytes",
+        field,
+        hash.0.len()
+        );
+}
+
+// Core and approval methods are implemented by hand so soulbound mode can
+// reject holder-initiated transfers and approvals.
+#[near_bindgen]
+impl NonFungibleTokenCore for Contract {
+    #[payable]
+    fn nft_transfer(
+        &mut self,
+        receiver_id: ValidAccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        ) {
+        self.assert_transferable();
+        self.nft_token.nft_transfer(receiver_id, token_id.clone(), approval_id, memo);
+        self.internal_sync_cert_owner(&token_id);
+    }
+
+    #[payable]
+    fn nft_transfer_call(
+        &mut self,
+        receiver_id: ValidAccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+        ) -> PromiseOrValue<bool> {
+        self.assert_transferable();
+        let result = self
+            .nft_token
+            .nft_transfer_call(receiver_id, token_id.clone(), approval_id, memo, msg);
+        self.internal_sync_cert_owner(&token_id);
+        result
+    }
+
+    fn nft_token(self, token_id: TokenId) -> Option<Token> {
+        self.nft_token.nft_token(token_id)
+    }
+
+    // Tokens only come from approved certificates through `MintCert` proposals.
+    fn mint(
+        &mut self,
+        _token_id: TokenId,
+        _token_owner_id: ValidAccountId,
+        _token_metadata: Option<TokenMetadata>,
+        ) -> Token {
+        env::panic(b"Certificates are minted through MintCert proposals")
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenResolver for Contract {
+    #[private]
+    fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approved_account_ids: Option<HashMap<AccountId, u64>>,
+        ) -> bool {
+        let transferred = self.nft_token.nft_resolve_transfer(
+            previous_owner_id,
+            receiver_id,
+            token_id.clone(),
+            approved_account_ids,
+            );
+        // The receiver may have handed the token back.
+        self.internal_sync_cert_owner(&token_id);
+        transferred
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenApproval for Contract {
+    #[payable]
+    fn nft_approve(
+        &mut self,
+        token_id: TokenId,
+        account_id: ValidAccountId,
+        msg: Option<String>,
+        ) -> Option<Promise> {
+        self.assert_transferable();
+        self.nft_token.nft_approve(token_id, account_id, msg)
+    }
+
+    #[payable]
+    fn nft_revoke(&mut self, token_id: TokenId, account_id: ValidAccountId) {
+        self.nft_token.nft_revoke(token_id, account_id)
+    }
+
+    #[payable]
+    fn nft_revoke_all(&mut self, token_id: TokenId) {
+        self.nft_token.nft_revoke_all(token_id)
+    }
+
+    fn nft_is_approved(
+        self,
+        token_id: TokenId,
+        approved_account_id: ValidAccountId,
+        approval_id: Option<u64>,
+        ) -> bool {
+        self.nft_token.nft_is_approved(token_id, approved_account_id, approval_id)
     }
 }
 
-near_contract_standards::impl_non_fungible_token_core!(Contract, nft_token);
-near_contract_standards::impl_non_fungible_token_approval!(Contract, nft_token);
 near_contract_standards::impl_non_fungible_token_enumeration!(Contract, nft_token);
 
 #[near_bindgen]
@@ -225,3 +1432,297 @@ impl NonFungibleTokenMetadataProvider for Contract {
         self.metadata.get().unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multisig::FoundationAction;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, Balance, MockedBlockchain};
+
+    const DEPOSIT: Balance = 10_000_000_000_000_000_000_000_000;
+    // Base64 of a 32 byte digest.
+    const MEDIA_HASH: &str = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+
+    fn set_caller(account: ValidAccountId) {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account)
+            .attached_deposit(DEPOSIT)
+            .build());
+    }
+
+    // alice runs the foundation and is its whole council, bob issues and
+    // charlie is the learner.
+    fn setup() -> Contract {
+        set_caller(accounts(0));
+        let mut contract = Contract::new();
+        register_issuer(&mut contract, accounts(1), "Near Academy");
+        return contract;
+    }
+
+    // Registers `issuer` with a storage balance and the course used by
+    // `content`.
+    fn register_issuer(contract: &mut Contract, issuer: ValidAccountId, issuer_name: &str) {
+        set_caller(accounts(0));
+        contract.propose(FoundationAction::NewIssuer {
+            issuer: issuer.clone(),
+            issuer_name: issuer_name.to_string(),
+        });
+        set_caller(issuer);
+        contract.storage_deposit(None, None);
+        contract.register_course(Course {
+            id: "rust-101".to_string(),
+            name: "Rust 101".to_string(),
+            level: None,
+            description: None,
+            credit_value: None,
+            validity_period: None,
+            artwork_uri: None,
+            artwork_hash: None,
+        });
+    }
+
+    fn content() -> CertContent {
+        return CertContent {
+            course_id: "rust-101".to_string(),
+            completion_date: U64(0),
+            grade: None,
+            credit_hours: None,
+            skills: vec![],
+        };
+    }
+
+    fn draft_cert(contract: &mut Contract, issuer: ValidAccountId, expires_at: Option<U64>) -> TokenId {
+        set_caller(issuer);
+        let cert = contract.new_cert(
+            "Charlie".to_string(),
+            accounts(2),
+            "https://example.com/cert.png".to_string(),
+            MEDIA_HASH.to_string(),
+            None,
+            expires_at,
+            content(),
+            );
+        return cert.id;
+    }
+
+    fn issue_cert(contract: &mut Contract) -> TokenId {
+        let cert_id = draft_cert(contract, accounts(1), None);
+        mint(contract, &cert_id);
+        return cert_id;
+    }
+
+    fn approve(contract: &mut Contract, cert_id: &TokenId) {
+        let issuer = contract.get_cert(cert_id.clone()).unwrap().issuer_account;
+        set_caller(issuer);
+        contract.submit_cert(cert_id.clone());
+        set_caller(accounts(0));
+        contract.approve_cert(cert_id.clone());
+    }
+
+    fn mint(contract: &mut Contract, cert_id: &TokenId) {
+        approve(contract, cert_id);
+        contract.propose(FoundationAction::MintCert { cert_id: cert_id.clone() });
+    }
+
+    fn status_of(contract: &Contract, cert_id: &TokenId) -> CertStatus {
+        return contract.get_cert(cert_id.clone()).unwrap().status;
+    }
+
+    fn cert_ids_of(contract: &Contract, account: ValidAccountId) -> Vec<TokenId> {
+        return contract.certs_of_owner(account, None, None).into_iter().map(|cert| cert.id).collect();
+    }
+
+    #[test]
+    fn status_follows_the_review_flow() {
+        assert!(CertStatus::Draft.can_transition_to(CertStatus::PendingReview));
+        assert!(CertStatus::PendingReview.can_transition_to(CertStatus::Approved));
+        assert!(CertStatus::PendingReview.can_transition_to(CertStatus::Rejected));
+        assert!(CertStatus::Rejected.can_transition_to(CertStatus::PendingReview));
+        assert!(CertStatus::Approved.can_transition_to(CertStatus::Minted));
+        assert!(CertStatus::Minted.can_transition_to(CertStatus::Revoked));
+        assert!(CertStatus::Minted.can_transition_to(CertStatus::Superseded));
+        assert!(CertStatus::Superseded.can_transition_to(CertStatus::Revoked));
+    }
+
+    #[test]
+    fn status_cannot_skip_review_or_leave_revoked() {
+        assert!(!CertStatus::Draft.can_transition_to(CertStatus::Approved));
+        assert!(!CertStatus::Draft.can_transition_to(CertStatus::Minted));
+        assert!(!CertStatus::PendingReview.can_transition_to(CertStatus::Minted));
+        assert!(!CertStatus::Rejected.can_transition_to(CertStatus::Approved));
+        assert!(!CertStatus::Minted.can_transition_to(CertStatus::Draft));
+        assert!(!CertStatus::Superseded.can_transition_to(CertStatus::Minted));
+        for status in [CertStatus::Draft, CertStatus::Approved, CertStatus::Minted] {
+            assert!(!CertStatus::Revoked.can_transition_to(status));
+        }
+    }
+
+    #[test]
+    fn recovery_moves_the_cert_between_owner_indexes() {
+        let mut contract = setup();
+        let cert_id = issue_cert(&mut contract);
+
+        set_caller(accounts(0));
+        contract.propose(FoundationAction::RecoverCert {
+            cert_id: cert_id.clone(),
+            new_owner: accounts(3),
+        });
+
+        assert!(cert_ids_of(&contract, accounts(2)).is_empty());
+        assert_eq!(cert_ids_of(&contract, accounts(3)), vec![cert_id.clone()]);
+        assert_eq!(contract.get_cert(cert_id.clone()).unwrap().owner_account, accounts(3));
+        assert_eq!(contract.nft_token.owner_by_id.get(&cert_id).unwrap(), accounts(3).to_string());
+    }
+
+    #[test]
+    fn renewal_supersedes_its_predecessor_once_minted() {
+        let mut contract = setup();
+        let cert_id = issue_cert(&mut contract);
+
+        set_caller(accounts(1));
+        let renewed = contract.renew_cert(cert_id.clone(), None, U64(1_000));
+        assert_eq!(status_of(&contract, &cert_id), CertStatus::Minted);
+
+        mint(&mut contract, &renewed.id);
+        let previous = contract.get_cert(cert_id.clone()).unwrap();
+        assert_eq!(previous.status, CertStatus::Superseded);
+        assert_eq!(previous.superseded_by, Some(renewed.id.clone()));
+        assert_eq!(status_of(&contract, &renewed.id), CertStatus::Minted);
+    }
+
+    #[test]
+    fn removing_an_issuer_drops_its_staff() {
+        let mut contract = setup();
+        set_caller(accounts(1));
+        contract.add_delegate(accounts(3), vec![StaffScope::Draft]);
+        assert!(contract.has_role(accounts(3), Role::IssuerStaff));
+
+        set_caller(accounts(0));
+        contract.propose(FoundationAction::RemoveIssuer { issuer: accounts(1) });
+
+        assert!(!contract.has_role(accounts(3), Role::IssuerStaff));
+        assert!(contract.delegation_of(accounts(3)).is_none());
+    }
+
+    #[test]
+    fn batch_mint_walks_approved_certs_and_skips_suspended_issuers() {
+        let mut contract = setup();
+        register_issuer(&mut contract, accounts(3), "Near Guild");
+
+        let first = draft_cert(&mut contract, accounts(1), None);
+        let suspended = draft_cert(&mut contract, accounts(3), None);
+        let second = draft_cert(&mut contract, accounts(1), None);
+        let draft = draft_cert(&mut contract, accounts(1), None);
+        for cert_id in [&first, &suspended, &second] {
+            approve(&mut contract, cert_id);
+        }
+        set_caller(accounts(0));
+        contract.suspend_issuer(accounts(3));
+
+        let outcome = contract.propose(FoundationAction::MintCertsBatch { from_index: None, limit: None });
+        let batch = outcome.result.unwrap();
+        let mut minted: Vec<TokenId> = near_sdk::serde_json::from_value(batch["minted"].clone()).unwrap();
+        minted.sort();
+        assert_eq!(minted, vec![first.clone(), second.clone()]);
+        assert!(batch["next_index"].is_null());
+        assert_eq!(status_of(&contract, &suspended), CertStatus::Approved);
+        assert_eq!(status_of(&contract, &draft), CertStatus::Draft);
+    }
+
+    #[test]
+    fn certs_issued_between_is_empty_for_an_inverted_range() {
+        let mut contract = setup();
+        issue_cert(&mut contract);
+
+        assert_eq!(contract.certs_issued_between(U64(0), U64(1), None, None).len(), 1);
+        assert!(contract.certs_issued_between(U64(1), U64(1), None, None).is_empty());
+        assert!(contract.certs_issued_between(U64(1), U64(0), None, None).is_empty());
+    }
+
+    #[test]
+    fn expiring_certs_come_soonest_first() {
+        let mut contract = setup();
+        let late = draft_cert(&mut contract, accounts(1), Some(U64(300)));
+        let soon = draft_cert(&mut contract, accounts(1), Some(U64(100)));
+        let later = draft_cert(&mut contract, accounts(1), Some(U64(200)));
+        let unminted = draft_cert(&mut contract, accounts(1), Some(U64(50)));
+        for cert_id in [&late, &soon, &later] {
+            mint(&mut contract, cert_id);
+        }
+
+        let expiring: Vec<TokenId> = contract
+            .expiring_certs(U64(250), None, None)
+            .into_iter()
+            .map(|cert| cert.id)
+            .collect();
+        assert_eq!(expiring, vec![soon.clone(), later.clone()]);
+        assert!(!expiring.contains(&unminted));
+
+        let page: Vec<TokenId> = contract
+            .expiring_certs(U64(1_000), Some(U64(1)), Some(1))
+            .into_iter()
+            .map(|cert| cert.id)
+            .collect();
+        assert_eq!(page, vec![later]);
+    }
+
+    #[test]
+    #[should_panic(expected = "media_hash must be base64 encoded")]
+    fn malformed_media_hash_is_rejected() {
+        let mut contract = setup();
+        set_caller(accounts(1));
+        contract.new_cert(
+            "Charlie".to_string(),
+            accounts(2),
+            "https://example.com/cert.png".to_string(),
+            "not base64!".to_string(),
+            None,
+            None,
+            content(),
+            );
+    }
+
+    fn values(level: &str, learner: &str) -> Vec<(&'static str, String)> {
+        return vec![
+            ("learner", learner.to_string()),
+            ("course", "Rust Smart Contracts".to_string()),
+            ("level", level.to_string()),
+            ("issuer", "Near Academy".to_string()),
+        ];
+    }
+
+    #[test]
+    fn render_template_fills_placeholders() {
+        assert_eq!(
+            render_template(DEFAULT_TITLE_TEMPLATE, &values("L1", "Jane Doe")),
+            "Rust Smart Contracts L1 — awarded to Jane Doe"
+            );
+    }
+
+    #[test]
+    fn render_template_closes_gaps_of_empty_placeholders() {
+        assert_eq!(
+            render_template(DEFAULT_TITLE_TEMPLATE, &values("", "Jane Doe")),
+            "Rust Smart Contracts — awarded to Jane Doe"
+            );
+        assert_eq!(render_template("{level} {course}", &values("", "Jane Doe")), "Rust Smart Contracts");
+    }
+
+    #[test]
+    fn render_template_does_not_expand_values() {
+        assert_eq!(
+            render_template("{learner} by {issuer}", &values("L1", "{issuer}")),
+            "{issuer} by Near Academy"
+            );
+    }
+
+    #[test]
+    fn render_template_keeps_line_breaks() {
+        assert_eq!(
+            render_template("{course}\n\n{level} awarded to {learner}", &values("", "Jane Doe")),
+            "Rust Smart Contracts\n\nawarded to Jane Doe"
+            );
+    }
+}
@@ -0,0 +1,440 @@
+use crate::*;
+use crate::roles::assert_managed_by_admin;
+use crate::views::page_bounds;
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::Value;
+
+/// Foundation actions that need `threshold` confirmations from the council
+/// before they run.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum FoundationAction {
+    NewIssuer { issuer: ValidAccountId, issuer_name: String },
+    RemoveIssuer { issuer: ValidAccountId },
+    ReinstateIssuer { issuer: ValidAccountId },
+    GrantRole { account: ValidAccountId, role: Role },
+    RevokeRole { account: ValidAccountId, role: Role },
+    MintCert { cert_id: TokenId },
+    MintCertsBatch { from_index: Option<U64>, limit: Option<u64> },
+    RevokeCert { cert_id: TokenId, reason: String },
+    RecoverCert { cert_id: TokenId, new_owner: ValidAccountId },
+    SetSoulbound { enabled: bool },
+    SetCouncil { members: Vec<ValidAccountId>, threshold: u32 },
+}
+
+impl FoundationAction {
+    // Checks that don't depend on contract state, so a malformed proposal is
+    // rejected before council members confirm it.
+    fn assert_valid(&self) {
+        match self {
+            FoundationAction::GrantRole { role, .. } | FoundationAction::RevokeRole { role, .. } => {
+                assert_managed_by_admin(*role);
+            }
+            FoundationAction::RevokeCert { reason, .. } => {
+                assert!(!reason.is_empty(), "Revocation reason is required");
+            }
+            FoundationAction::SetCouncil { members, threshold } => {
+                Council::new(members.clone(), *threshold);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Council {
+    pub members: Vec<ValidAccountId>,
+    pub threshold: u32,
+}
+
+impl Council {
+    pub fn new(members: Vec<ValidAccountId>, threshold: u32) -> Self {
+        assert!(!members.is_empty(), "Council needs at least one member");
+        assert!(
+            threshold >= 1 && threshold as usize <= members.len(),
+            "Threshold must be between 1 and the number of council members"
+            );
+        for (i, member) in members.iter().enumerate() {
+            assert!(!members[..i].contains(member), "Duplicate council member {}", member);
+        }
+        return Council { members, threshold };
+    }
+
+    /// Hands the seat of `old` to `new`. Does nothing when `old` is not a
+    /// member; the threshold is lowered if `new` already held a seat.
+    pub fn replace_member(&mut self, old: &ValidAccountId, new: &ValidAccountId) {
+        if !self.members.contains(old) {
+            return;
+        }
+        self.members.retain(|member| member != old && member != new);
+        self.members.push(new.clone());
+        self.threshold = std::cmp::min(self.threshold, self.members.len() as u32);
+    }
+
+    pub fn is_member(&self, account: &AccountId) -> bool {
+        return self.members.iter().any(|member| member.to_string() == *account);
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Proposal {
+    pub id: U64,
+    pub action: FoundationAction,
+    pub proposer: AccountId,
+    pub confirmations: Vec<Confirmation>,
+    pub created_at: U64,
+}
+
+/// A council member's confirmation and the storage deposit it paid, which is
+/// refunded once the proposal executes or is cancelled.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Confirmation {
+    pub account: AccountId,
+    pub deposit: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalOutcome {
+    pub proposal_id: U64,
+    pub executed: bool,
+    /// What the action returned, when it was executed by this call.
+    pub result: Option<Value>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Creates a proposal confirmed by the caller. It executes right away when
+    /// the council threshold is already met. The caller pays for the storage
+    /// of the proposal until it executes or is cancelled, and for whatever the
+    /// action stores, except for minting.
+    #[payable]
+    pub fn propose(&mut self, action: FoundationAction) -> ProposalOutcome {
+        let member = self.assert_council_member();
+        action.assert_valid();
+        let initial_storage_usage = env::storage_usage();
+
+        let mut proposal = Proposal {
+            id: U64(self.next_proposal_id),
+            action,
+            proposer: member.clone(),
+            confirmations: vec![],
+            created_at: U64(env::block_timestamp()),
+        };
+        self.next_proposal_id += 1;
+        let storage_used = self.internal_add_confirmation(&mut proposal, member, initial_storage_usage);
+        log_event("proposal_created", json!({
+            "proposal_id": proposal.id,
+            "proposer": proposal.proposer,
+        }));
+
+        return self.internal_execute_if_confirmed(proposal, storage_used);
+    }
+
+    #[payable]
+    pub fn confirm(&mut self, proposal_id: U64) -> ProposalOutcome {
+        let member = self.assert_council_member();
+        let mut proposal = self.proposals.get(&proposal_id.0).expect("Proposal not found");
+        assert!(
+            !proposal.confirmations.iter().any(|confirmation| confirmation.account == member),
+            "Proposal is already confirmed by this account"
+            );
+        let initial_storage_usage = env::storage_usage();
+
+        let storage_used = self.internal_add_confirmation(&mut proposal, member.clone(), initial_storage_usage);
+        log_event("proposal_confirmed", json!({
+            "proposal_id": proposal_id,
+            "member": member,
+        }));
+
+        return self.internal_execute_if_confirmed(proposal, storage_used);
+    }
+
+    /// Drops a pending proposal and refunds the deposits of its confirmations.
+    pub fn cancel_proposal(&mut self, proposal_id: U64) {
+        let proposal = self.proposals.get(&proposal_id.0).expect("Proposal not found");
+        assert_eq!(
+            proposal.proposer,
+            env::predecessor_account_id(),
+            "Only the proposer can cancel this proposal"
+            );
+
+        self.proposals.remove(&proposal_id.0);
+        refund_confirmations(&proposal.confirmations);
+        log_event("proposal_cancelled", json!({ "proposal_id": proposal_id }));
+    }
+
+    pub fn get_council(&self) -> Council {
+        return self.council.clone();
+    }
+
+    pub fn pending_proposals(&self, from_index: Option<U64>, limit: Option<u64>) -> Vec<Proposal> {
+        let proposals = self.proposals.values_as_vector();
+        let (start, end) = page_bounds(proposals.len(), from_index, limit);
+        return (start..end).map(|index| proposals.get(index).unwrap()).collect();
+    }
+
+    pub fn proposal_confirmations(&self, proposal_id: U64) -> Vec<AccountId> {
+        return self
+            .proposals
+            .get(&proposal_id.0)
+            .map_or(vec![], |proposal| {
+                proposal
+                    .confirmations
+                    .into_iter()
+                    .map(|confirmation| confirmation.account)
+                    .collect()
+            });
+    }
+}
+
+impl Contract {
+    fn assert_council_member(&self) -> AccountId {
+        let predecessor = env::predecessor_account_id();
+        assert!(
+            self.council.is_member(&predecessor),
+            "Only council members can call this fn"
+            );
+        return predecessor;
+    }
+
+    // Saves the proposal with `member`'s confirmation, whose deposit is what
+    // it added to storage, the proposal record included for the first one.
+    // Returns the bytes added.
+    fn internal_add_confirmation(&mut self, proposal: &mut Proposal, member: AccountId, initial_storage_usage: u64) -> u64 {
+        proposal.confirmations.push(Confirmation { account: member, deposit: U128(0) });
+        self.proposals.insert(&proposal.id.0, proposal);
+
+        let storage_used = env::storage_usage() - initial_storage_usage;
+        proposal.confirmations.last_mut().unwrap().deposit = U128(env::storage_byte_cost() * Balance::from(storage_used));
+        self.proposals.insert(&proposal.id.0, proposal);
+        return storage_used;
+    }
+
+    // Only confirmations from current members count, so a council change
+    // does not let former members push pending proposals through.
+    fn internal_execute_if_confirmed(&mut self, proposal: Proposal, storage_used: u64) -> ProposalOutcome {
+        let confirmations = proposal
+            .confirmations
+            .iter()
+            .filter(|confirmation| self.council.is_member(&confirmation.account))
+            .count();
+
+        if confirmations < self.council.threshold as usize {
+            refund_deposit(storage_used);
+            return ProposalOutcome {
+                proposal_id: proposal.id,
+                executed: false,
+                result: None,
+            };
+        }
+
+        // The proposal is removed as it runs, so the caller doesn't pay for its
+        // own confirmation and the earlier ones get their deposits back.
+        self.proposals.remove(&proposal.id.0);
+        let (_, earlier_confirmations) = proposal.confirmations.split_last().unwrap();
+        refund_confirmations(earlier_confirmations);
+
+        let mut action_storage_used = 0;
+        let result = self.internal_execute(proposal.action, &mut action_storage_used);
+        log_event("proposal_executed", json!({ "proposal_id": proposal.id }));
+
+        refund_deposit(action_storage_used);
+        return ProposalOutcome {
+            proposal_id: proposal.id,
+            executed: true,
+            result: Some(result),
+        };
+    }
+
+    // Minting settles its storage against the issuer's balance, and a revoked
+    // token's storage goes back to the issuer. Whatever else an action stores
+    // is added to `storage_used`, which the caller pays.
+    fn internal_execute(&mut self, action: FoundationAction, storage_used: &mut u64) -> Value {
+        match &action {
+            FoundationAction::MintCert { cert_id } => {
+                return json!(self.internal_mint_cert(cert_id.clone()));
+            }
+            FoundationAction::MintCertsBatch { from_index, limit } => {
+                return json!(self.internal_mint_certs_batch(*from_index, *limit));
+            }
+            FoundationAction::RevokeCert { cert_id, .. } => {
+                let cert = self.internal_get_cert(cert_id);
+                self.internal_burn_revoked(&cert);
+            }
+            _ => {}
+        }
+
+        let initial_storage_usage = env::storage_usage();
+        let result = match action {
+            FoundationAction::NewIssuer { issuer, issuer_name } => {
+                json!(self.internal_new_issuer(issuer, issuer_name))
+            }
+            FoundationAction::RemoveIssuer { issuer } => json!(self.internal_remove_issuer(issuer)),
+            FoundationAction::ReinstateIssuer { issuer } => {
+                json!(self.internal_set_issuer_status(&issuer, IssuerStatus::Active))
+            }
+            FoundationAction::GrantRole { account, role } => {
+                json!(self.internal_grant_managed_role(&account, role))
+            }
+            FoundationAction::RevokeRole { account, role } => {
+                json!(self.internal_revoke_managed_role(&account, role))
+            }
+            FoundationAction::RevokeCert { cert_id, reason } => {
+                let cert = self.internal_get_cert(&cert_id);
+                json!(self.internal_revoke_cert(cert, reason))
+            }
+            FoundationAction::RecoverCert { cert_id, new_owner } => {
+                self.internal_recover_cert(cert_id, new_owner);
+                json!(null)
+            }
+            FoundationAction::SetSoulbound { enabled } => {
+                self.soulbound = enabled;
+                json!(enabled)
+            }
+            FoundationAction::SetCouncil { members, threshold } => {
+                self.council = Council::new(members, threshold);
+                json!(self.council)
+            }
+            FoundationAction::MintCert { .. } | FoundationAction::MintCertsBatch { .. } => unreachable!(),
+        };
+        *storage_used += env::storage_usage().saturating_sub(initial_storage_usage);
+        return result;
+    }
+}
+
+fn refund_confirmations(confirmations: &[Confirmation]) {
+    for confirmation in confirmations {
+        if confirmation.deposit.0 > 0 {
+            Promise::new(confirmation.account.clone()).transfer(confirmation.deposit.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, MockedBlockchain};
+
+    const DEPOSIT: Balance = 10_000_000_000_000_000_000_000_000;
+
+    fn set_caller(account: ValidAccountId) {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account)
+            .attached_deposit(DEPOSIT)
+            .build());
+    }
+
+    // alice deploys and hands control to a 2-of-3 council of alice, bob and charlie.
+    fn setup() -> Contract {
+        set_caller(accounts(0));
+        let mut contract = Contract::new();
+        contract.propose(FoundationAction::SetCouncil {
+            members: vec![accounts(0), accounts(1), accounts(2)],
+            threshold: 2,
+        });
+        return contract;
+    }
+
+    #[test]
+    fn executes_once_threshold_is_met() {
+        let mut contract = setup();
+        let outcome = contract.propose(FoundationAction::SetSoulbound { enabled: false });
+        assert!(!outcome.executed);
+        assert!(contract.is_soulbound());
+
+        set_caller(accounts(1));
+        let outcome = contract.confirm(outcome.proposal_id);
+        assert!(outcome.executed);
+        assert!(!contract.is_soulbound());
+        assert!(contract.pending_proposals(None, None).is_empty());
+    }
+
+    #[test]
+    fn confirmations_hold_their_storage_deposit_until_cancelled() {
+        let mut contract = setup();
+        let outcome = contract.propose(FoundationAction::SetSoulbound { enabled: false });
+        let proposal = contract.pending_proposals(None, None).pop().unwrap();
+        assert_eq!(proposal.confirmations[0].account, accounts(0).to_string());
+        assert!(proposal.confirmations[0].deposit.0 > 0);
+
+        contract.cancel_proposal(outcome.proposal_id);
+        assert!(contract.pending_proposals(None, None).is_empty());
+        assert!(contract.is_soulbound());
+    }
+
+    #[test]
+    #[should_panic(expected = "Proposal is already confirmed by this account")]
+    fn rejects_double_confirmation() {
+        let mut contract = setup();
+        let outcome = contract.propose(FoundationAction::SetSoulbound { enabled: false });
+        contract.confirm(outcome.proposal_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only council members can call this fn")]
+    fn rejects_proposals_from_non_members() {
+        let mut contract = setup();
+        set_caller(accounts(3));
+        contract.propose(FoundationAction::SetSoulbound { enabled: false });
+    }
+
+    #[test]
+    fn ignores_confirmations_of_former_members() {
+        let mut contract = setup();
+        set_caller(accounts(2));
+        let pending = contract.propose(FoundationAction::SetSoulbound { enabled: false });
+
+        set_caller(accounts(0));
+        let change = contract.propose(FoundationAction::SetCouncil {
+            members: vec![accounts(0), accounts(1)],
+            threshold: 2,
+        });
+        set_caller(accounts(1));
+        assert!(contract.confirm(change.proposal_id).executed);
+
+        set_caller(accounts(0));
+        assert!(!contract.confirm(pending.proposal_id).executed);
+        set_caller(accounts(1));
+        assert!(contract.confirm(pending.proposal_id).executed);
+    }
+
+    #[test]
+    fn accept_foundation_moves_the_council_seat() {
+        set_caller(accounts(0));
+        let mut contract = Contract::new();
+        contract.propose_foundation(accounts(3));
+
+        set_caller(accounts(3));
+        contract.accept_foundation();
+        assert_eq!(contract.get_council().members, vec![accounts(3)]);
+        assert!(contract.propose(FoundationAction::SetSoulbound { enabled: false }).executed);
+    }
+
+    #[test]
+    fn replace_member_keeps_threshold_reachable() {
+        let mut council = Council::new(vec![accounts(0), accounts(1)], 2);
+        council.replace_member(&accounts(0), &accounts(1));
+        assert_eq!(council.members, vec![accounts(1)]);
+        assert_eq!(council.threshold, 1);
+
+        council.replace_member(&accounts(2), &accounts(3));
+        assert_eq!(council.members, vec![accounts(1)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Threshold must be between 1 and the number of council members")]
+    fn council_rejects_unreachable_threshold() {
+        Council::new(vec![accounts(0), accounts(1)], 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate council member")]
+    fn council_rejects_duplicate_members() {
+        Council::new(vec![accounts(0), accounts(0)], 1);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::*;
+
+/// Limits on how many certificates an issuer may create. `None` means
+/// unlimited.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct IssuerQuota {
+    pub max_total: Option<u64>,
+    pub max_per_window: Option<u64>,
+    /// Length of the rate limit window in nanoseconds.
+    pub window_duration: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Default)]
+pub struct IssuanceUsage {
+    pub total: u64,
+    pub window_start: u64,
+    pub window_count: u64,
+}
+
+impl IssuanceUsage {
+    // Fixed windows: the count resets once the current window has elapsed.
+    fn at(&self, quota: &IssuerQuota, timestamp: u64) -> IssuanceUsage {
+        let mut usage = self.clone();
+        if timestamp >= usage.window_start + quota.window_duration.0 {
+            usage.window_start = timestamp;
+            usage.window_count = 0;
+        }
+        return usage;
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuotaView {
+    pub quota: Option<IssuerQuota>,
+    pub issued_total: u64,
+    pub remaining_total: Option<u64>,
+    pub remaining_in_window: Option<u64>,
+    pub window_resets_at: Option<U64>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets or, with `None`, clears the issuance quota of an issuer.
+    #[payable]
+    pub fn set_issuer_quota(&mut self, issuer: ValidAccountId, quota: Option<IssuerQuota>) {
+        self.assert_role(Role::FoundationAdmin);
+        self.internal_get_issuer(&issuer);
+        let initial_storage_usage = env::storage_usage();
+
+        match quota {
+            Some(quota) => {
+                if quota.max_per_window.is_some() {
+                    assert!(quota.window_duration.0 > 0, "window_duration must be positive");
+                }
+                self.issuer_quotas.insert(issuer.as_ref(), &quota);
+            }
+            None => {
+                self.issuer_quotas.remove(issuer.as_ref());
+            }
+        }
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    pub fn remaining_quota(&self, issuer: ValidAccountId) -> QuotaView {
+        let usage = self.issuance_usage.get(issuer.as_ref()).unwrap_or_default();
+        let quota = match self.issuer_quotas.get(issuer.as_ref()) {
+            Some(quota) => quota,
+            None => {
+                return QuotaView {
+                    quota: None,
+                    issued_total: usage.total,
+                    remaining_total: None,
+                    remaining_in_window: None,
+                    window_resets_at: None,
+                };
+            }
+        };
+
+        let usage = usage.at(&quota, env::block_timestamp());
+        return QuotaView {
+            issued_total: usage.total,
+            remaining_total: quota.max_total.map(|max| max.saturating_sub(usage.total)),
+            remaining_in_window: quota
+                .max_per_window
+                .map(|max| max.saturating_sub(usage.window_count)),
+            window_resets_at: quota
+                .max_per_window
+                .map(|_| U64(usage.window_start + quota.window_duration.0)),
+            quota: Some(quota),
+        };
+    }
+}
+
+impl Contract {
+    /// Panics unless `issuer` may create `count` more certificates right now.
+    pub(crate) fn assert_quota_allows(&self, issuer: &ValidAccountId, count: u64) {
+        let quota = match self.issuer_quotas.get(issuer.as_ref()) {
+            Some(quota) => quota,
+            None => return,
+        };
+        let usage = self
+            .issuance_usage
+            .get(issuer.as_ref())
+            .unwrap_or_default()
+            .at(&quota, env::block_timestamp());
+
+        if let Some(max_total) = quota.max_total {
+            assert!(
+                usage.total + count <= max_total,
+                "Issuance quota exceeded: {} of {} certificates already issued",
+                usage.total,
+                max_total
+                );
+        }
+        if let Some(max_per_window) = quota.max_per_window {
+            assert!(
+                usage.window_count + count <= max_per_window,
+                "Issuance rate limit exceeded: {} of {} certificates issued in the current window",
+                usage.window_count,
+                max_per_window
+                );
+        }
+    }
+
+    pub(crate) fn internal_consume_quota(&mut self, issuer: &ValidAccountId) {
+        self.assert_quota_allows(issuer, 1);
+
+        let usage = self.issuance_usage.get(issuer.as_ref()).unwrap_or_default();
+        let mut usage = match self.issuer_quotas.get(issuer.as_ref()) {
+            Some(quota) => usage.at(&quota, env::block_timestamp()),
+            None => usage,
+        };
+        usage.total += 1;
+        usage.window_count += 1;
+        self.issuance_usage.insert(issuer.as_ref(), &usage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, MockedBlockchain};
+
+    fn set_time(timestamp: u64) {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(timestamp)
+            .build());
+    }
+
+    fn setup(quota: IssuerQuota) -> Contract {
+        set_time(0);
+        let mut contract = Contract::new();
+        contract.issuer_quotas.insert(accounts(1).as_ref(), &quota);
+        return contract;
+    }
+
+    fn rate_limit(max_per_window: u64) -> IssuerQuota {
+        return IssuerQuota {
+            max_total: None,
+            max_per_window: Some(max_per_window),
+            window_duration: U64(100),
+        };
+    }
+
+    #[test]
+    fn window_count_resets_once_the_window_elapsed() {
+        let quota = rate_limit(2);
+        let usage = IssuanceUsage { total: 5, window_start: 10, window_count: 2 };
+
+        let within = usage.at(&quota, 109);
+        assert_eq!((within.window_start, within.window_count), (10, 2));
+
+        let next = usage.at(&quota, 110);
+        assert_eq!((next.window_start, next.window_count, next.total), (110, 0, 5));
+    }
+
+    #[test]
+    #[should_panic(expected = "Issuance rate limit exceeded")]
+    fn rate_limit_applies_within_a_window() {
+        let mut contract = setup(rate_limit(2));
+        contract.internal_consume_quota(&accounts(1));
+        contract.internal_consume_quota(&accounts(1));
+        set_time(99);
+        contract.internal_consume_quota(&accounts(1));
+    }
+
+    #[test]
+    fn rate_limit_frees_up_in_the_next_window() {
+        let mut contract = setup(rate_limit(2));
+        contract.internal_consume_quota(&accounts(1));
+        contract.internal_consume_quota(&accounts(1));
+        set_time(100);
+        contract.internal_consume_quota(&accounts(1));
+
+        let view = contract.remaining_quota(accounts(1));
+        assert_eq!(view.issued_total, 3);
+        assert_eq!(view.remaining_in_window, Some(1));
+        assert_eq!(view.window_resets_at, Some(U64(200)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Issuance quota exceeded")]
+    fn total_quota_is_never_reset() {
+        let mut contract = setup(IssuerQuota {
+            max_total: Some(1),
+            max_per_window: None,
+            window_duration: U64(0),
+        });
+        contract.internal_consume_quota(&accounts(1));
+        set_time(1_000);
+        contract.internal_consume_quota(&accounts(1));
+    }
+}
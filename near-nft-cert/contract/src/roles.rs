@@ -0,0 +1,223 @@
+use crate::*;
+
+/// Roles an account can hold. Every change method asserts the role it needs:
+///
+/// - `FoundationAdmin`: suspends issuers, renames them, sets their quotas and
+///   removes their staff. Anything more sensitive, such as granting roles,
+///   minting or recovering certificates, goes through council proposals.
+/// - `Reviewer`: approves or rejects submitted certificates.
+/// - `Issuer`: creates and submits its own certificates. Granted and dropped
+///   by `NewIssuer` and `RemoveIssuer` council proposals.
+/// - `IssuerStaff`: acts on behalf of an issuer within the scopes it was
+///   delegated. Managed by the issuer with `add_delegate`/`remove_delegate`.
+/// - `Auditor`: read-only; recorded for off-chain tooling, no change method
+///   requires it.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    FoundationAdmin,
+    Reviewer,
+    Issuer,
+    IssuerStaff,
+    Auditor,
+}
+
+/// What an issuer lets a staff delegate do on its behalf.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum StaffScope {
+    /// Create draft certificates.
+    Draft,
+    /// Submit certificates for review and renew them.
+    Issue,
+    Revoke,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StaffDelegation {
+    pub issuer: ValidAccountId,
+    pub scopes: Vec<StaffScope>,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn has_role(&self, account: ValidAccountId, role: Role) -> bool {
+        return self.roles_of(account).contains(&role);
+    }
+
+    pub fn roles_of(&self, account: ValidAccountId) -> Vec<Role> {
+        return self.roles.get(account.as_ref()).unwrap_or_default();
+    }
+
+    /// Lets `account` act for the calling issuer within `scopes`. Calling it
+    /// again for the same delegate replaces its scopes.
+    pub fn add_delegate(&mut self, account: ValidAccountId, scopes: Vec<StaffScope>) -> StaffDelegation {
+        let issuer = self.assert_role(Role::Issuer);
+        self.assert_active_issuer(&issuer);
+        assert!(!scopes.is_empty(), "At least one scope is required");
+        assert!(self.issuers.get(&account).is_none(), "Issuers can't be staff delegates");
+        if let Some(current) = self.issuer_staff.get(account.as_ref()) {
+            assert_eq!(current.issuer, issuer, "Account is already staff of another issuer");
+        }
+        let initial_storage_usage = env::storage_usage();
+
+        let delegation = StaffDelegation { issuer: issuer.clone(), scopes };
+        self.issuer_staff.insert(account.as_ref(), &delegation);
+        self.internal_grant_role(account.as_ref(), Role::IssuerStaff);
+
+        let mut staff = self.staff_per_issuer.get(issuer.as_ref()).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::StaffPerIssuerInner {
+                account_hash: env::sha256(issuer.to_string().as_bytes()),
+            })
+        });
+        staff.insert(account.as_ref());
+        self.staff_per_issuer.insert(issuer.as_ref(), &staff);
+
+        self.internal_charge_storage(&issuer.to_string(), initial_storage_usage);
+        return delegation;
+    }
+
+    /// Drops a staff delegate. Callable by its issuer, even while suspended,
+    /// or by a foundation admin.
+    pub fn remove_delegate(&mut self, account: ValidAccountId) -> bool {
+        let delegation = match self.issuer_staff.get(account.as_ref()) {
+            Some(delegation) => delegation,
+            None => return false,
+        };
+        if env::predecessor_account_id() != delegation.issuer.to_string() {
+            self.assert_role(Role::FoundationAdmin);
+        }
+        let initial_storage_usage = env::storage_usage();
+
+        self.internal_remove_delegate(&delegation.issuer, account.as_ref());
+
+        if self.storage_accounts.get(&delegation.issuer.to_string()).is_some() {
+            self.internal_charge_storage(&delegation.issuer.to_string(), initial_storage_usage);
+        }
+        return true;
+    }
+
+    pub fn delegation_of(&self, account: ValidAccountId) -> Option<StaffDelegation> {
+        return self.issuer_staff.get(account.as_ref());
+    }
+}
+
+impl Contract {
+    /// Panics unless the predecessor holds `role`, and returns the predecessor.
+    pub(crate) fn assert_role(&self, role: Role) -> ValidAccountId {
+        let predecessor = ValidAccountId::try_from(env::predecessor_account_id()).unwrap();
+        assert!(
+            self.has_role(predecessor.clone(), role),
+            "Only accounts with the {:?} role can call this fn",
+            role
+            );
+        return predecessor;
+    }
+
+    /// The active issuer the predecessor acts for, either as the issuer itself
+    /// or as one of its staff delegated `scope`.
+    pub(crate) fn assert_acting_issuer(&self, scope: StaffScope) -> ValidAccountId {
+        let predecessor = ValidAccountId::try_from(env::predecessor_account_id()).unwrap();
+        let roles = self.roles_of(predecessor.clone());
+
+        let issuer = if roles.contains(&Role::Issuer) {
+            predecessor
+        } else if roles.contains(&Role::IssuerStaff) {
+            let delegation = self.issuer_staff.get(predecessor.as_ref()).unwrap();
+            assert!(
+                delegation.scopes.contains(&scope),
+                "Staff is not delegated the {:?} scope",
+                scope
+                );
+            delegation.issuer
+        } else {
+            env::panic(b"Only accounts with the Issuer or IssuerStaff role can call this fn")
+        };
+        self.assert_active_issuer(&issuer);
+        return issuer;
+    }
+
+    pub(crate) fn assert_active_issuer(&self, issuer: &ValidAccountId) {
+        let issuer = self.issuers.get(issuer).expect("Issuer not found");
+        assert_eq!(issuer.status, IssuerStatus::Active, "Issuer is suspended");
+    }
+
+    /// Grants `FoundationAdmin`, `Reviewer` or `Auditor` through a `GrantRole`
+    /// proposal. Issuers and their staff have dedicated methods.
+    pub(crate) fn internal_grant_managed_role(&mut self, account: &ValidAccountId, role: Role) -> bool {
+        assert_managed_by_admin(role);
+        return self.internal_grant_role(account.as_ref(), role);
+    }
+
+    pub(crate) fn internal_revoke_managed_role(&mut self, account: &ValidAccountId, role: Role) -> bool {
+        assert_managed_by_admin(role);
+        if role == Role::FoundationAdmin {
+            assert_ne!(
+                account, &self.contract_foundation,
+                "The contract foundation always keeps FoundationAdmin"
+                );
+        }
+        return self.internal_revoke_role(account.as_ref(), role);
+    }
+
+    pub(crate) fn internal_remove_delegate(&mut self, issuer: &ValidAccountId, account: &AccountId) {
+        self.issuer_staff.remove(account);
+        self.internal_revoke_role(account, Role::IssuerStaff);
+
+        if let Some(mut staff) = self.staff_per_issuer.get(issuer.as_ref()) {
+            staff.remove(account);
+            if staff.is_empty() {
+                self.staff_per_issuer.remove(issuer.as_ref());
+            } else {
+                self.staff_per_issuer.insert(issuer.as_ref(), &staff);
+            }
+        }
+    }
+
+    // Drops every delegate of a removed issuer, so they don't silently regain
+    // their powers if the account is registered again.
+    pub(crate) fn internal_remove_all_delegates(&mut self, issuer: &ValidAccountId) {
+        let mut staff = match self.staff_per_issuer.remove(issuer.as_ref()) {
+            Some(staff) => staff,
+            None => return,
+        };
+        for account in staff.to_vec() {
+            self.issuer_staff.remove(&account);
+            self.internal_revoke_role(&account, Role::IssuerStaff);
+        }
+        staff.clear();
+    }
+
+    pub(crate) fn internal_grant_role(&mut self, account: &AccountId, role: Role) -> bool {
+        let mut roles = self.roles.get(account).unwrap_or_default();
+        if roles.contains(&role) {
+            return false;
+        }
+        roles.push(role);
+        self.roles.insert(account, &roles);
+        return true;
+    }
+
+    pub(crate) fn internal_revoke_role(&mut self, account: &AccountId, role: Role) -> bool {
+        let mut roles = self.roles.get(account).unwrap_or_default();
+        if !roles.contains(&role) {
+            return false;
+        }
+        roles.retain(|r| *r != role);
+        if roles.is_empty() {
+            self.roles.remove(account);
+        } else {
+            self.roles.insert(account, &roles);
+        }
+        return true;
+    }
+}
+
+pub(crate) fn assert_managed_by_admin(role: Role) {
+    match role {
+        Role::Issuer => env::panic(b"Issuers are managed with NewIssuer and RemoveIssuer proposals"),
+        Role::IssuerStaff => env::panic(b"Issuer staff is managed with add_delegate and remove_delegate"),
+        _ => {}
+    }
+}
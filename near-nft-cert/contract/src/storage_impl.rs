@@ -0,0 +1,195 @@
+use crate::*;
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
+use near_sdk::assert_one_yocto;
+use near_sdk::json_types::U128;
+
+// Minimum balance an issuer registers with, enough for a handful of certificates.
+const STORAGE_MIN_BYTES: u64 = 2_000;
+// Storage a minted token takes besides its metadata: the owner entries, the
+// approvals map and the status transition.
+const MINT_STORAGE_OVERHEAD_BYTES: u64 = 800;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StorageAccount {
+    pub total: Balance,
+    pub used_bytes: u64,
+}
+
+impl StorageAccount {
+    pub fn used(&self) -> Balance {
+        return env::storage_byte_cost() * Balance::from(self.used_bytes);
+    }
+
+    pub fn available(&self) -> Balance {
+        return self.total.saturating_sub(self.used());
+    }
+
+    fn to_balance(&self) -> StorageBalance {
+        return StorageBalance {
+            total: U128(self.total),
+            available: U128(self.available()),
+        };
+    }
+}
+
+fn storage_min_balance() -> Balance {
+    return env::storage_byte_cost() * Balance::from(STORAGE_MIN_BYTES);
+}
+
+impl Contract {
+    /// Settles the storage used since `initial_storage_usage` against the
+    /// issuer's pre-funded balance. Freed storage is credited back.
+    pub(crate) fn internal_charge_storage(&mut self, issuer: &AccountId, initial_storage_usage: u64) {
+        let mut account = self
+            .storage_accounts
+            .get(issuer)
+            .unwrap_or_else(|| {
+                env::panic(format!("{} has no storage balance, call storage_deposit first", issuer).as_bytes())
+            });
+
+        let storage_usage = env::storage_usage();
+        if storage_usage >= initial_storage_usage {
+            account.used_bytes += storage_usage - initial_storage_usage;
+        } else {
+            account.used_bytes = account.used_bytes.saturating_sub(initial_storage_usage - storage_usage);
+        }
+
+        assert!(
+            account.used() <= account.total,
+            "Storage balance of {} is too low: {} yoctoNEAR needed, {} deposited",
+            issuer,
+            account.used(),
+            account.total
+            );
+        self.storage_accounts.insert(issuer, &account);
+    }
+
+    /// Whether the issuer's available balance covers minting `cert`.
+    pub(crate) fn internal_can_pay_mint(&self, issuer: &AccountId, cert: &Certificate) -> bool {
+        let account = match self.storage_accounts.get(issuer) {
+            Some(account) => account,
+            None => return false,
+        };
+        let metadata_bytes = cert.metadata.try_to_vec().unwrap().len() as u64;
+        let mint_cost = env::storage_byte_cost() * Balance::from(metadata_bytes + MINT_STORAGE_OVERHEAD_BYTES);
+        return account.available() >= mint_cost;
+    }
+}
+
+#[near_bindgen]
+impl StorageManagement for Contract {
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<ValidAccountId>,
+        registration_only: Option<bool>,
+        ) -> StorageBalance {
+        let account_id = account_id
+            .unwrap_or_else(|| ValidAccountId::try_from(env::predecessor_account_id()).unwrap());
+        assert!(
+            self.issuers.get(&account_id).is_some(),
+            "Only issuers can hold a storage balance"
+            );
+
+        let amount = env::attached_deposit();
+        let account_id: AccountId = account_id.into();
+        let mut account = match self.storage_accounts.get(&account_id) {
+            Some(account) => {
+                if registration_only.unwrap_or(false) {
+                    if amount > 0 {
+                        Promise::new(env::predecessor_account_id()).transfer(amount);
+                    }
+                    return account.to_balance();
+                }
+                account
+            }
+            None => {
+                let min_balance = storage_min_balance();
+                assert!(
+                    amount >= min_balance,
+                    "The attached deposit is less than the minimum storage balance of {}",
+                    min_balance
+                    );
+                StorageAccount { total: 0, used_bytes: 0 }
+            }
+        };
+
+        if registration_only.unwrap_or(false) {
+            let min_balance = storage_min_balance();
+            account.total = min_balance;
+            if amount > min_balance {
+                Promise::new(env::predecessor_account_id()).transfer(amount - min_balance);
+            }
+        } else {
+            account.total += amount;
+        }
+
+        self.storage_accounts.insert(&account_id, &account);
+        return account.to_balance();
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+
+        let account_id = env::predecessor_account_id();
+        let mut account = self
+            .storage_accounts
+            .get(&account_id)
+            .unwrap_or_else(|| env::panic(format!("{} is not registered", account_id).as_bytes()));
+
+        let available = account.available();
+        let amount = amount.map_or(available, |amount| amount.0);
+        assert!(
+            amount <= available,
+            "The amount is greater than the available storage balance"
+            );
+
+        if amount > 0 {
+            account.total -= amount;
+            self.storage_accounts.insert(&account_id, &account);
+            Promise::new(account_id).transfer(amount);
+        }
+        return account.to_balance();
+    }
+
+    /// Unregistering is only possible once the issuer no longer pays for any
+    /// stored certificates, so `force` is not supported.
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        assert!(!force.unwrap_or(false), "Forced unregistration is not supported");
+
+        let account_id = env::predecessor_account_id();
+        return match self.storage_accounts.get(&account_id) {
+            Some(account) => {
+                assert_eq!(
+                    account.used_bytes, 0,
+                    "Can't unregister while certificates still use storage"
+                    );
+                self.storage_accounts.remove(&account_id);
+                if account.total > 0 {
+                    Promise::new(account_id).transfer(account.total);
+                }
+                true
+            }
+            None => false,
+        };
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        return StorageBalanceBounds {
+            min: U128(storage_min_balance()),
+            max: None,
+        };
+    }
+
+    fn storage_balance_of(&self, account_id: ValidAccountId) -> Option<StorageBalance> {
+        return self
+            .storage_accounts
+            .get(&account_id.to_string())
+            .map(|account| account.to_balance());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::*;
+use near_sdk::collections::Vector;
+
+const DEFAULT_PAGE_LIMIT: u64 = 50;
+
+/// Outcome of `verify_cert`. `hash_matches` and `owner_matches` are `None`
+/// when the caller did not ask for that check.
+#[derive(Serialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CertVerification {
+    pub exists: bool,
+    pub status: Option<CertStatus>,
+    pub issuer: Option<ValidAccountId>,
+    pub issuer_active: bool,
+    /// Whether a reviewer approved the certificate at some point.
+    pub approved: bool,
+    pub revoked: bool,
+    pub expired: bool,
+    pub hash_matches: Option<bool>,
+    /// Account currently holding the token, `None` when it is not minted.
+    pub holder: Option<AccountId>,
+    pub owner_matches: Option<bool>,
+    /// Minted by an active issuer, within its validity window and passing
+    /// every requested check.
+    pub valid: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn cert_lists(&self, from_index: Option<U64>, limit: Option<u64>) -> Vec<Certificate> {
+        let (start, end) = page_bounds(self.next_cert_id, from_index, limit);
+        return (start..end)
+            .map(|index| self.internal_get_cert(&index.to_string()))
+            .collect();
+    }
+
+    pub fn certs_of_owner(
+        &self,
+        account: ValidAccountId,
+        from_index: Option<U64>,
+        limit: Option<u64>,
+        ) -> Vec<Certificate> {
+        return match self.certs_per_owner.get(&account) {
+            Some(cert_ids) => self.internal_certs_page(cert_ids.as_vector(), from_index, limit),
+            None => vec![],
+        };
+    }
+
+    pub fn certs_by_issuer(
+        &self,
+        issuer: ValidAccountId,
+        from_index: Option<U64>,
+        limit: Option<u64>,
+        ) -> Vec<Certificate> {
+        return match self.certs_per_issuer.get(issuer.as_ref()) {
+            Some(cert_ids) => self.internal_certs_page(cert_ids.as_vector(), from_index, limit),
+            None => vec![],
+        };
+    }
+
+    pub fn certs_by_status(
+        &self,
+        status: CertStatus,
+        from_index: Option<U64>,
+        limit: Option<u64>,
+        ) -> Vec<Certificate> {
+        return match self.certs_per_status.get(&status) {
+            Some(cert_ids) => self.internal_certs_page(cert_ids.as_vector(), from_index, limit),
+            None => vec![],
+        };
+    }
+
+    /// Certificates issued in `[from, to)`. Ids are handed out in issuance
+    /// order, so the range is found by binary search over ids.
+    pub fn certs_issued_between(
+        &self,
+        from: U64,
+        to: U64,
+        from_index: Option<U64>,
+        limit: Option<u64>,
+        ) -> Vec<Certificate> {
+        if to.0 <= from.0 {
+            return vec![];
+        }
+        let first = self.internal_first_issued_at_or_after(from.0);
+        let last = self.internal_first_issued_at_or_after(to.0);
+        let (start, end) = page_bounds(last - first, from_index, limit);
+        return (first + start..first + end)
+            .map(|index| self.internal_get_cert(&index.to_string()))
+            .collect();
+    }
+
+    pub fn issuer_lists(&self, from_index: Option<U64>, limit: Option<u64>) -> Vec<Issuer> {
+        let issuers = self.issuers.values_as_vector();
+        let (start, end) = page_bounds(issuers.len(), from_index, limit);
+        return (start..end).map(|index| issuers.get(index).unwrap()).collect();
+    }
+
+    pub fn get_issuer(&self, account: ValidAccountId) -> Option<Issuer> {
+        return self.issuers.get(&account);
+    }
+
+    /// Whether `account` is a registered issuer that is currently allowed to issue.
+    pub fn is_issuer(&self, account: ValidAccountId) -> bool {
+        return self.is_active_issuer(&account);
+    }
+
+    pub fn issuer_cert_counts(&self, account: ValidAccountId) -> Option<IssuerCertCounts> {
+        return self.issuer_cert_counts.get(account.as_ref());
+    }
+
+    /// Checks a certificate presented by a learner, optionally against the
+    /// hash of its artwork and the account claiming to hold it.
+    pub fn verify_cert(
+        &self,
+        token_id: TokenId,
+        media_hash: Option<Base64VecU8>,
+        owner_account: Option<ValidAccountId>,
+        ) -> CertVerification {
+        let cert = match self.certs_map.get(&token_id) {
+            Some(cert) => cert,
+            None => return CertVerification::default(),
+        };
+
+        let issuer_active = self.is_active_issuer(&cert.issuer_account);
+        let approved = cert
+            .history
+            .iter()
+            .any(|transition| transition.to == CertStatus::Approved);
+        let revoked = cert.status == CertStatus::Revoked;
+        let validity = cert.validity_at(env::block_timestamp());
+        let hash_matches = media_hash
+            .map(|media_hash| cert.metadata.media_hash.as_ref().map(|hash| &hash.0) == Some(&media_hash.0));
+        let holder = self.nft_token.owner_by_id.get(&token_id);
+        let owner_matches = owner_account.map(|account| holder.as_ref() == Some(&account.to_string()));
+
+        let valid = cert.status == CertStatus::Minted
+            && issuer_active
+            && validity == CertValidity::Valid
+            && hash_matches != Some(false)
+            && owner_matches != Some(false);
+        return CertVerification {
+            exists: true,
+            status: Some(cert.status),
+            issuer: Some(cert.issuer_account),
+            issuer_active,
+            approved,
+            revoked,
+            expired: validity == CertValidity::Expired,
+            hash_matches,
+            holder,
+            owner_matches,
+            valid,
+        };
+    }
+
+    /// Minted certificates whose validity ends before `before`, including ones
+    /// that have already expired, soonest first.
+    pub fn expiring_certs(&self, before: U64, from_index: Option<U64>, limit: Option<u64>) -> Vec<Certificate> {
+        return self
+            .minted_by_expiry
+            .iter()
+            .take_while(|((expires_at, _), _)| *expires_at < before.0)
+            .skip(from_index.map_or(0, |i| i.0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .map(|((_, cert_id), _)| self.internal_get_cert(&cert_id))
+            .collect();
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_certs_page(
+        &self,
+        cert_ids: &Vector<TokenId>,
+        from_index: Option<U64>,
+        limit: Option<u64>,
+        ) -> Vec<Certificate> {
+        let (start, end) = page_bounds(cert_ids.len(), from_index, limit);
+        return (start..end)
+            .map(|index| self.internal_get_cert(&cert_ids.get(index).unwrap()))
+            .collect();
+    }
+
+    // Index of the first certificate issued at or after `timestamp`, or
+    // `next_cert_id` when there is none.
+    fn internal_first_issued_at_or_after(&self, timestamp: u64) -> u64 {
+        let (mut low, mut high) = (0, self.next_cert_id);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let issued_at = parse_timestamp(&self.internal_get_cert(&mid.to_string()).metadata.issued_at)
+                .unwrap_or(0);
+            if issued_at < timestamp {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        return low;
+    }
+}
+
+pub(crate) fn page_bounds(len: u64, from_index: Option<U64>, limit: Option<u64>) -> (u64, u64) {
+    let start = std::cmp::min(from_index.map_or(0, |i| i.0), len);
+    let end = std::cmp::min(start.saturating_add(limit.unwrap_or(DEFAULT_PAGE_LIMIT)), len);
+    return (start, end);
+}